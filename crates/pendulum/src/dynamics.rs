@@ -2,6 +2,18 @@ use std::fmt::Debug;
 
 use cgmath::{vec3, Vector3};
 
+pub use self::{
+    combinator::{DynamicsExt, Offset, Rotate, Sequence, Shift, Sum, TimeScale},
+    harmonic::{Harmonic, Sinusoid},
+    hermite::{Hermite3, HermiteLimits, VelocityEstimator},
+    spline::CubicSpline,
+};
+
+mod combinator;
+mod harmonic;
+mod hermite;
+mod spline;
+
 pub trait Dynamics: Debug {
     fn x(&self, t: f64) -> Vector3<f64>;
 
@@ -27,7 +39,9 @@ impl Dynamics for FixedPoint {
     }
 }
 
-/// Quadratic Bezier curve
+/// Cubic Bezier curve
+///
+/// The end velocity is left free, use `Hermite3` to match it too.
 #[derive(Debug, Clone)]
 pub struct Bezier4 {
     p0: Vector3<f64>,
@@ -162,6 +176,24 @@ impl UniformT {
     }
 }
 
+/// Check that `v` and `a` of `d` are the derivatives of `x` and `v` at `t`
+#[cfg(test)]
+pub(crate) fn assert_consistent<D: Dynamics>(d: &D, t: f64) {
+    use cgmath::assert_relative_eq;
+
+    let eps = 1e-5;
+    assert_relative_eq!(
+        (d.x(t + eps) - d.x(t - eps)) / (2.0 * eps),
+        d.v(t),
+        epsilon = 1e-6
+    );
+    assert_relative_eq!(
+        (d.v(t + eps) - d.v(t - eps)) / (2.0 * eps),
+        d.a(t),
+        epsilon = 1e-6
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,273 @@
+use cgmath::{Matrix3, Vector3};
+
+use super::Dynamics;
+
+impl<D: Dynamics + ?Sized> Dynamics for Box<D> {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        (**self).x(t)
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        (**self).v(t)
+    }
+
+    fn a(&self, t: f64) -> Vector3<f64> {
+        (**self).a(t)
+    }
+}
+
+/// Superposition of two motions
+#[derive(Debug, Clone)]
+pub struct Sum<A, B>(pub A, pub B);
+
+impl<A: Dynamics, B: Dynamics> Dynamics for Sum<A, B> {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        self.0.x(t) + self.1.x(t)
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        self.0.v(t) + self.1.v(t)
+    }
+
+    fn a(&self, t: f64) -> Vector3<f64> {
+        self.0.a(t) + self.1.a(t)
+    }
+}
+
+/// `inner` delayed by `delay` seconds
+#[derive(Debug, Clone)]
+pub struct Shift<D> {
+    inner: D,
+    delay: f64,
+}
+
+impl<D: Dynamics> Dynamics for Shift<D> {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        self.inner.x(t - self.delay)
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        self.inner.v(t - self.delay)
+    }
+
+    fn a(&self, t: f64) -> Vector3<f64> {
+        self.inner.a(t - self.delay)
+    }
+}
+
+/// `inner` played `rate` times faster
+#[derive(Debug, Clone)]
+pub struct TimeScale<D> {
+    inner: D,
+    rate: f64,
+}
+
+impl<D: Dynamics> Dynamics for TimeScale<D> {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        self.inner.x(t * self.rate)
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        self.inner.v(t * self.rate) * self.rate
+    }
+
+    fn a(&self, t: f64) -> Vector3<f64> {
+        self.inner.a(t * self.rate) * (self.rate * self.rate)
+    }
+}
+
+/// `inner` translated by `offset`
+#[derive(Debug, Clone)]
+pub struct Offset<D> {
+    inner: D,
+    offset: Vector3<f64>,
+}
+
+impl<D: Dynamics> Dynamics for Offset<D> {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        self.inner.x(t) + self.offset
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        self.inner.v(t)
+    }
+
+    fn a(&self, t: f64) -> Vector3<f64> {
+        self.inner.a(t)
+    }
+}
+
+/// `inner` rotated around the origin
+#[derive(Debug, Clone)]
+pub struct Rotate<D> {
+    inner: D,
+    rotation: Matrix3<f64>,
+}
+
+impl<D: Dynamics> Dynamics for Rotate<D> {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        self.rotation * self.inner.x(t)
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        self.rotation * self.inner.v(t)
+    }
+
+    fn a(&self, t: f64) -> Vector3<f64> {
+        self.rotation * self.inner.a(t)
+    }
+}
+
+pub trait DynamicsExt: Dynamics + Sized {
+    fn plus<D: Dynamics>(self, other: D) -> Sum<Self, D> {
+        Sum(self, other)
+    }
+
+    fn shift(self, delay: f64) -> Shift<Self> {
+        Shift { inner: self, delay }
+    }
+
+    fn time_scale(self, rate: f64) -> TimeScale<Self> {
+        TimeScale { inner: self, rate }
+    }
+
+    fn offset(self, offset: Vector3<f64>) -> Offset<Self> {
+        Offset {
+            inner: self,
+            offset,
+        }
+    }
+
+    fn rotate(self, rotation: Matrix3<f64>) -> Rotate<Self> {
+        Rotate {
+            inner: self,
+            rotation,
+        }
+    }
+}
+
+impl<D: Dynamics> DynamicsExt for D {}
+
+/// Segments switched at their start times
+///
+/// Each segment sees the time elapsed since its own start, so a segment
+/// can be written as if it started at `t = 0`.
+/// Matching x/v/a at the switching times is up to the segments.
+#[derive(Debug)]
+pub struct Sequence {
+    segments: Vec<(f64, Box<dyn Dynamics>)>,
+}
+
+impl Sequence {
+    /// Start with `first` starting at `start`, which also covers the time before it
+    pub fn new<D: Dynamics + 'static>(start: f64, first: D) -> Sequence {
+        Sequence {
+            segments: vec![(start, Box::new(first))],
+        }
+    }
+
+    /// Append `segment` starting at `start`, which must be after the last start
+    pub fn then<D: Dynamics + 'static>(
+        mut self,
+        start: f64,
+        segment: D,
+    ) -> Result<Sequence, String> {
+        if let Some(&(last, _)) = self.segments.last() {
+            if start <= last {
+                return Err(format!(
+                    "segment starts at {} but the previous one starts at {}",
+                    start, last
+                ));
+            }
+        }
+        self.segments.push((start, Box::new(segment)));
+        Ok(self)
+    }
+
+    /// Active segment and its local time, the first one before it starts
+    fn segment(&self, t: f64) -> (&dyn Dynamics, f64) {
+        // never empty since `new`
+        let i = self
+            .segments
+            .partition_point(|&(start, _)| start <= t)
+            .max(1);
+        let (start, segment) = &self.segments[i - 1];
+        (segment.as_ref(), t - start)
+    }
+}
+
+impl Dynamics for Sequence {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        let (d, t) = self.segment(t);
+        d.x(t)
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        let (d, t) = self.segment(t);
+        d.v(t)
+    }
+
+    fn a(&self, t: f64) -> Vector3<f64> {
+        let (d, t) = self.segment(t);
+        d.a(t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamics::{assert_consistent, FixedPoint, Hermite3, Oscillate1d};
+    use cgmath::{assert_relative_eq, vec3, Deg, Zero};
+
+    #[test]
+    fn test_combinators() {
+        let osc = Oscillate1d::new(vec3(1.0, 0.0, 0.0), vec3(0.0, 0.5, 0.0), 2.0, 0.0);
+        let d = osc
+            .clone()
+            .time_scale(1.5)
+            .shift(0.3)
+            .plus(osc.shift(0.1))
+            .rotate(Matrix3::from_angle_z(Deg(30.0)))
+            .offset(vec3(1.0, 2.0, 3.0));
+        for t in [0.0, 0.4, 1.7] {
+            assert_consistent(&d, t);
+        }
+        let fixed = FixedPoint(vec3(1.0, 1.0, 1.0)).offset(vec3(0.0, -1.0, 0.0));
+        assert_eq!(fixed.x(3.0), vec3(1.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn test_sequence() {
+        let side = vec3(0.5, 0.0, 0.0);
+        let seq = Sequence::new(0.0, FixedPoint(Vector3::zero()))
+            .then(
+                1.0,
+                Oscillate1d::new(Vector3::zero(), vec3(0.1, 0.0, 0.0), 5.0, 0.0),
+            )
+            .unwrap()
+            .then(
+                6.0,
+                Hermite3::new(
+                    Vector3::zero(),
+                    Vector3::zero(),
+                    side,
+                    Vector3::zero(),
+                    0.0,
+                    1.0,
+                ),
+            )
+            .unwrap()
+            .then(7.0, FixedPoint(side))
+            .unwrap();
+        assert_eq!(seq.x(-1.0), Vector3::zero());
+        assert_eq!(seq.x(0.5), Vector3::zero());
+        assert_relative_eq!(seq.v(1.0), vec3(0.5, 0.0, 0.0));
+        assert_consistent(&seq, 3.3);
+        assert_relative_eq!(seq.x(6.5), side * 0.5);
+        assert_eq!(seq.x(10.0), side);
+        assert!(Sequence::new(1.0, FixedPoint(side))
+            .then(1.0, FixedPoint(side))
+            .is_err());
+        assert_eq!(Sequence::new(2.0, FixedPoint(side)).x(0.0), side);
+    }
+}
@@ -0,0 +1,196 @@
+use cgmath::{vec3, ElementWise, Vector3, Zero};
+use serde::{Deserialize, Serialize};
+
+use super::Dynamics;
+
+/// `amplitude[k] * sin(θ(t) + phase[k])` on each axis k, where `θ(t) = omega t + sweep t^2 / 2`
+///
+/// A non-zero `sweep` makes a chirp whose angular frequency grows by `sweep` every second.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sinusoid {
+    pub amplitude: Vector3<f64>,
+    #[serde(default = "Vector3::zero")]
+    pub phase: Vector3<f64>,
+    pub omega: f64,
+    #[serde(default)]
+    pub sweep: f64,
+}
+
+impl Sinusoid {
+    pub fn new(amplitude: Vector3<f64>, phase: Vector3<f64>, omega: f64) -> Sinusoid {
+        Sinusoid {
+            amplitude,
+            phase,
+            omega,
+            sweep: 0.0,
+        }
+    }
+
+    /// `a cos(omega t) + b sin(omega t)`, an ellipse in general
+    pub fn ellipse(a: Vector3<f64>, b: Vector3<f64>, omega: f64) -> Sinusoid {
+        let amplitude = vec3(a.x.hypot(b.x), a.y.hypot(b.y), a.z.hypot(b.z));
+        let phase = vec3(a.x.atan2(b.x), a.y.atan2(b.y), a.z.atan2(b.z));
+        Sinusoid::new(amplitude, phase, omega)
+    }
+
+    pub fn with_sweep(self, sweep: f64) -> Sinusoid {
+        Sinusoid { sweep, ..self }
+    }
+
+    /// Per axis (sin, cos) of the phase, and θ'(t)
+    fn eval(&self, t: f64) -> (Vector3<f64>, Vector3<f64>, f64) {
+        let theta = (self.omega + 0.5 * self.sweep * t) * t;
+        let (sx, cx) = (theta + self.phase.x).sin_cos();
+        let (sy, cy) = (theta + self.phase.y).sin_cos();
+        let (sz, cz) = (theta + self.phase.z).sin_cos();
+        (
+            vec3(sx, sy, sz),
+            vec3(cx, cy, cz),
+            self.omega + self.sweep * t,
+        )
+    }
+}
+
+/// Sum of sinusoids, e.g. circles, ellipses, Lissajous curves and chirps
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Harmonic {
+    terms: Vec<Sinusoid>,
+}
+
+impl Harmonic {
+    pub fn new(terms: Vec<Sinusoid>) -> Harmonic {
+        Harmonic { terms }
+    }
+
+    /// Horizontal circle of `radius` around the origin, counterclockwise seen from +y
+    pub fn circle(radius: f64, omega: f64) -> Harmonic {
+        Harmonic::new(vec![Sinusoid::ellipse(
+            vec3(radius, 0.0, 0.0),
+            vec3(0.0, 0.0, -radius),
+            omega,
+        )])
+    }
+
+    /// Independent frequency and phase on each axis
+    pub fn lissajous(
+        amplitude: Vector3<f64>,
+        omega: Vector3<f64>,
+        phase: Vector3<f64>,
+    ) -> Harmonic {
+        Harmonic::new(vec![
+            Sinusoid::new(
+                vec3(amplitude.x, 0.0, 0.0),
+                vec3(phase.x, 0.0, 0.0),
+                omega.x,
+            ),
+            Sinusoid::new(
+                vec3(0.0, amplitude.y, 0.0),
+                vec3(0.0, phase.y, 0.0),
+                omega.y,
+            ),
+            Sinusoid::new(
+                vec3(0.0, 0.0, amplitude.z),
+                vec3(0.0, 0.0, phase.z),
+                omega.z,
+            ),
+        ])
+    }
+
+    /// Oscillation along `amplitude` whose angular frequency sweeps
+    /// from `omega_start` to `omega_end` in `duration` seconds
+    pub fn chirp(
+        amplitude: Vector3<f64>,
+        omega_start: f64,
+        omega_end: f64,
+        duration: f64,
+    ) -> Harmonic {
+        Harmonic::new(vec![Sinusoid::new(amplitude, Vector3::zero(), omega_start)
+            .with_sweep((omega_end - omega_start) / duration)])
+    }
+
+    pub fn push(&mut self, term: Sinusoid) {
+        self.terms.push(term);
+    }
+
+    pub fn terms(&self) -> &[Sinusoid] {
+        &self.terms
+    }
+}
+
+impl Dynamics for Harmonic {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        let mut x = Vector3::zero();
+        for term in self.terms.iter() {
+            let (sin, _, _) = term.eval(t);
+            x += term.amplitude.mul_element_wise(sin);
+        }
+        x
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        let mut v = Vector3::zero();
+        for term in self.terms.iter() {
+            let (_, cos, w) = term.eval(t);
+            v += term.amplitude.mul_element_wise(cos) * w;
+        }
+        v
+    }
+
+    fn a(&self, t: f64) -> Vector3<f64> {
+        let mut a = Vector3::zero();
+        for term in self.terms.iter() {
+            let (sin, cos, w) = term.eval(t);
+            a += term
+                .amplitude
+                .mul_element_wise(cos * term.sweep - sin * (w * w));
+        }
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamics::{assert_consistent, Oscillate1d};
+    use cgmath::{assert_relative_eq, InnerSpace};
+
+    #[test]
+    fn test_ellipse_matches_oscillate1d() {
+        let a = vec3(1.0, -0.5, 0.0);
+        let b = vec3(0.0, 0.3, 2.0);
+        let h = Harmonic::new(vec![Sinusoid::ellipse(a, b, 3.0)]);
+        let o = Oscillate1d::new(a, b, 3.0, 0.0);
+        for t in [0.0, 0.3, 1.9] {
+            assert_relative_eq!(h.x(t), o.x(t), epsilon = 1e-12);
+            assert_relative_eq!(h.v(t), o.v(t), epsilon = 1e-12);
+            assert_relative_eq!(h.a(t), o.a(t), epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_circle() {
+        let c = Harmonic::circle(0.5, 2.0);
+        for t in [0.0, 0.4, 1.3] {
+            assert_relative_eq!(c.x(t).magnitude(), 0.5, epsilon = 1e-12);
+            assert_relative_eq!(c.x(t).y, 0.0);
+            assert_relative_eq!(c.a(t), -c.x(t) * 4.0, epsilon = 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_derivatives() {
+        let mut h = Harmonic::lissajous(
+            vec3(1.0, 0.5, 0.2),
+            vec3(3.0, 2.0, 5.0),
+            vec3(0.5, 0.0, 1.0),
+        );
+        h.push(Sinusoid::new(vec3(0.1, 0.1, 0.0), vec3(0.0, 1.0, 0.0), 7.0).with_sweep(0.3));
+        let chirp = Harmonic::chirp(vec3(0.1, 0.0, 0.0), 1.0, 10.0, 5.0);
+        for t in [0.0, 0.7, 2.5] {
+            assert_consistent(&h, t);
+            assert_consistent(&chirp, t);
+        }
+        // the instantaneous frequency at the end of the sweep
+        assert_relative_eq!(chirp.terms()[0].eval(5.0).2, 10.0, epsilon = 1e-12);
+    }
+}
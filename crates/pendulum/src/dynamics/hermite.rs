@@ -0,0 +1,228 @@
+use std::collections::VecDeque;
+
+use cgmath::{InnerSpace, Vector3, Zero};
+use serde::{Deserialize, Serialize};
+
+use super::{Dynamics, UniformT};
+
+/// Cubic Hermite curve matching position and velocity at both ends
+#[derive(Debug, Copy, Clone)]
+pub struct Hermite3 {
+    // x(s) = c0 + c1 s + c2 s^2 + c3 s^3 for s in [0, 1]
+    c0: Vector3<f64>,
+    c1: Vector3<f64>,
+    c2: Vector3<f64>,
+    c3: Vector3<f64>,
+    ut: UniformT,
+}
+
+/// Upper bounds of |acceleration| and |jerk| along a `Hermite3`
+#[derive(Debug, Copy, Clone, Default, Serialize, Deserialize)]
+pub struct HermiteLimits {
+    pub max_acceleration: Option<f64>,
+    pub max_jerk: Option<f64>,
+}
+
+impl Hermite3 {
+    pub fn new(
+        x0: Vector3<f64>,
+        v0: Vector3<f64>,
+        x1: Vector3<f64>,
+        v1: Vector3<f64>,
+        t0: f64,
+        t1: f64,
+    ) -> Hermite3 {
+        let ut = UniformT::new(t0, t1);
+        let v0 = v0 * ut.dt;
+        let v1 = v1 * ut.dt;
+        Hermite3 {
+            c0: x0,
+            c1: v0,
+            c2: (x1 - x0) * 3.0 - v0 * 2.0 - v1,
+            c3: (x0 - x1) * 2.0 + v0 + v1,
+            ut,
+        }
+    }
+
+    /// Same as `new`, but falls short of `(x1, v1)` when the curve would break `limits`
+    ///
+    /// The end is pulled toward the free flight `(x0 + v0 (t1 - t0), v0)`,
+    /// which has neither acceleration nor jerk.
+    pub fn with_limits(
+        x0: Vector3<f64>,
+        v0: Vector3<f64>,
+        x1: Vector3<f64>,
+        v1: Vector3<f64>,
+        t0: f64,
+        t1: f64,
+        limits: &HermiteLimits,
+    ) -> Hermite3 {
+        let mut curve = Hermite3::new(x0, v0, x1, v1, t0, t1);
+        let mut scale = 1.0f64;
+        if let Some(max) = limits.max_acceleration {
+            let a = curve.a(t0).magnitude().max(curve.a(t1).magnitude());
+            if a > max {
+                scale = scale.min(max / a);
+            }
+        }
+        if let Some(max) = limits.max_jerk {
+            let j = curve.jerk().magnitude();
+            if j > max {
+                scale = scale.min(max / j);
+            }
+        }
+        if scale < 1.0 {
+            // both acceleration and jerk are linear in the end state
+            curve.c2 *= scale;
+            curve.c3 *= scale;
+        }
+        curve
+    }
+
+    pub fn jerk(&self) -> Vector3<f64> {
+        self.c3 * 6.0 / (self.ut.dt * self.ut.dt * self.ut.dt)
+    }
+}
+
+impl Dynamics for Hermite3 {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        let s = self.ut.t(t);
+        self.c0 + (self.c1 + (self.c2 + self.c3 * s) * s) * s
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        let s = self.ut.t(t);
+        (self.c1 + (self.c2 * 2.0 + self.c3 * (3.0 * s)) * s) / self.ut.dt
+    }
+
+    fn a(&self, t: f64) -> Vector3<f64> {
+        let s = self.ut.t(t);
+        (self.c2 * 2.0 + self.c3 * (6.0 * s)) / (self.ut.dt * self.ut.dt)
+    }
+}
+
+/// Velocity of recently sampled positions by least squares
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VelocityEstimator {
+    window: f64,
+    samples: VecDeque<(f64, Vector3<f64>)>,
+}
+
+impl VelocityEstimator {
+    /// Use the samples of the last `window` seconds
+    pub fn new(window: f64) -> VelocityEstimator {
+        VelocityEstimator {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    pub fn push(&mut self, t: f64, x: Vector3<f64>) {
+        self.samples.push_back((t, x));
+        while let Some(&(t0, _)) = self.samples.front() {
+            if t - t0 <= self.window || self.samples.len() <= 2 {
+                break;
+            }
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn estimate(&self) -> Vector3<f64> {
+        let n = self.samples.len() as f64;
+        if n < 2.0 {
+            return Vector3::zero();
+        }
+        let t_mean = self.samples.iter().map(|(t, _)| t).sum::<f64>() / n;
+        let x_mean = self
+            .samples
+            .iter()
+            .fold(Vector3::zero(), |acc, (_, x)| acc + x)
+            / n;
+        let mut txx = Vector3::zero();
+        let mut tt = 0.0;
+        for &(t, x) in self.samples.iter() {
+            txx += (x - x_mean) * (t - t_mean);
+            tt += (t - t_mean) * (t - t_mean);
+        }
+        if tt == 0.0 {
+            return Vector3::zero();
+        }
+        txx / tt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{assert_relative_eq, vec3};
+
+    #[test]
+    fn test_hermite() {
+        let x0 = vec3(1.0, 2.0, 3.0);
+        let v0 = vec3(0.5, 0.0, -1.0);
+        let x1 = vec3(-2.0, 4.0, 5.0);
+        let v1 = vec3(1.0, 1.0, 0.0);
+        let h = Hermite3::new(x0, v0, x1, v1, 3.0, 5.0);
+        assert_relative_eq!(h.x(3.0), x0, epsilon = 1e-12);
+        assert_relative_eq!(h.v(3.0), v0, epsilon = 1e-12);
+        assert_relative_eq!(h.x(5.0), x1, epsilon = 1e-12);
+        assert_relative_eq!(h.v(5.0), v1, epsilon = 1e-12);
+
+        let eps = 1e-6;
+        let t = 3.7;
+        assert_relative_eq!(
+            (h.x(t + eps) - h.x(t - eps)) / (2.0 * eps),
+            h.v(t),
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            (h.v(t + eps) - h.v(t - eps)) / (2.0 * eps),
+            h.a(t),
+            epsilon = 1e-6
+        );
+        assert_relative_eq!(
+            (h.a(t + eps) - h.a(t - eps)) / (2.0 * eps),
+            h.jerk(),
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn test_hermite_limits() {
+        let x0 = vec3(0.0, 0.0, 0.0);
+        let v0 = vec3(1.0, 0.0, 0.0);
+        let x1 = vec3(0.0, 1.0, 0.0);
+        let v1 = vec3(0.0, 0.0, 0.0);
+        let limits = HermiteLimits {
+            max_acceleration: Some(2.0),
+            max_jerk: Some(5.0),
+        };
+        let h = Hermite3::with_limits(x0, v0, x1, v1, 0.0, 0.5, &limits);
+        assert_relative_eq!(h.x(0.0), x0);
+        assert_relative_eq!(h.v(0.0), v0);
+        assert!(h.a(0.0).magnitude() <= 2.0 + 1e-12);
+        assert!(h.a(0.5).magnitude() <= 2.0 + 1e-12);
+        assert!(h.jerk().magnitude() <= 5.0 + 1e-12);
+
+        let free = Hermite3::with_limits(x0, v0, x1, v1, 0.0, 0.5, &HermiteLimits::default());
+        assert_relative_eq!(free.x(0.5), x1);
+        assert_relative_eq!(free.v(0.5), v1);
+    }
+
+    #[test]
+    fn test_velocity_estimator() {
+        let mut e = VelocityEstimator::new(0.1);
+        assert_eq!(e.estimate(), Vector3::zero());
+        let v = vec3(0.3, -1.0, 2.0);
+        for i in 0..20 {
+            let t = i as f64 / 60.0;
+            e.push(t, vec3(1.0, 2.0, 3.0) + v * t);
+        }
+        assert!(e.samples.len() < 20);
+        assert_relative_eq!(e.estimate(), v, epsilon = 1e-9);
+    }
+}
@@ -0,0 +1,215 @@
+use cgmath::{vec3, Vector3, Zero};
+
+use super::Dynamics;
+use crate::pendulum::thomas;
+
+/// Natural cubic spline through timestamped waypoints
+///
+/// C2-continuous inside the waypoints, and holds the first/last waypoint outside.
+#[derive(Debug, Clone)]
+pub struct CubicSpline {
+    t: Vec<f64>,
+    p: Vec<Vector3<f64>>,
+    // second derivatives at the waypoints
+    m: Vec<Vector3<f64>>,
+}
+
+impl CubicSpline {
+    pub fn new(waypoints: &[(f64, Vector3<f64>)]) -> Result<CubicSpline, String> {
+        let n = waypoints.len();
+        if n < 2 {
+            return Err(String::from("at least 2 waypoints are needed"));
+        }
+        for (i, ((ta, _), (tb, _))) in waypoints.iter().zip(waypoints.iter().skip(1)).enumerate() {
+            if tb <= ta {
+                return Err(format!(
+                    "waypoint {}: time {} is not after the previous {}",
+                    i + 1,
+                    tb,
+                    ta
+                ));
+            }
+        }
+        let t = waypoints.iter().map(|(t, _)| *t).collect::<Vec<_>>();
+        let p = waypoints.iter().map(|(_, p)| *p).collect::<Vec<_>>();
+        let h = t.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+
+        let mut m = vec![Vector3::zero(); n];
+        if n > 2 {
+            // 6 * (slope difference) = h[i-1] M[i-1] + 2 (h[i-1] + h[i]) M[i] + h[i] M[i+1]
+            let a = (1..n - 1)
+                .map(|i| 2.0 * (h[i - 1] + h[i]))
+                .collect::<Vec<_>>();
+            let b = (1..n - 2).map(|i| -h[i]).collect::<Vec<_>>();
+            let c = (1..n - 1)
+                .map(|i| ((p[i + 1] - p[i]) / h[i] - (p[i] - p[i - 1]) / h[i - 1]) * 6.0)
+                .collect::<Vec<_>>();
+            let solve = |f: fn(&Vector3<f64>) -> f64| -> Vec<f64> {
+                let c = c.iter().map(f).collect::<Vec<_>>();
+                if c.len() == 1 {
+                    vec![c[0] / a[0]]
+                } else {
+                    thomas(&a, &b, &c)
+                }
+            };
+            let (mx, my, mz) = (solve(|v| v.x), solve(|v| v.y), solve(|v| v.z));
+            for i in 0..n - 2 {
+                m[i + 1] = vec3(mx[i], my[i], mz[i]);
+            }
+        }
+        Ok(CubicSpline { t, p, m })
+    }
+
+    /// Read `t,x,y,z` lines
+    ///
+    /// Empty lines and lines starting with `#` are skipped,
+    /// and so is the first other line if none of its fields is a number, as a header.
+    pub fn from_csv(data: &str) -> Result<CubicSpline, String> {
+        let mut waypoints = Vec::new();
+        let mut first = true;
+        for (i, line) in data.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let header = std::mem::take(&mut first)
+                && line.split(',').all(|w| w.trim().parse::<f64>().is_err());
+            let values = line
+                .split(',')
+                .map(|w| w.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>();
+            match values {
+                Ok(values) if values.len() == 4 => {
+                    waypoints.push((values[0], vec3(values[1], values[2], values[3])))
+                }
+                Ok(values) => {
+                    return Err(format!(
+                        "line {}: expect 4 values, but got {}",
+                        i + 1,
+                        values.len()
+                    ))
+                }
+                Err(_) if header => (),
+                Err(err) => return Err(format!("line {}: {}", i + 1, err)),
+            }
+        }
+        CubicSpline::new(&waypoints)
+    }
+
+    /// Segment index and the local coordinates (A, B, h)
+    fn segment(&self, t: f64) -> Option<(usize, f64, f64, f64)> {
+        let n = self.t.len();
+        if t < self.t[0] || self.t[n - 1] < t {
+            return None;
+        }
+        let i = self.t.partition_point(|&ti| ti <= t).clamp(1, n - 1) - 1;
+        let h = self.t[i + 1] - self.t[i];
+        let b = (t - self.t[i]) / h;
+        Some((i, 1.0 - b, b, h))
+    }
+}
+
+impl Dynamics for CubicSpline {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        match self.segment(t) {
+            Some((i, a, b, h)) => {
+                self.p[i] * a
+                    + self.p[i + 1] * b
+                    + (self.m[i] * (a * a * a - a) + self.m[i + 1] * (b * b * b - b)) * (h * h)
+                        / 6.0
+            }
+            None if t < self.t[0] => self.p[0],
+            None => self.p[self.p.len() - 1],
+        }
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        match self.segment(t) {
+            Some((i, a, b, h)) => {
+                (self.p[i + 1] - self.p[i]) / h
+                    + (self.m[i + 1] * (3.0 * b * b - 1.0) - self.m[i] * (3.0 * a * a - 1.0)) * h
+                        / 6.0
+            }
+            None => Vector3::zero(),
+        }
+    }
+
+    fn a(&self, t: f64) -> Vector3<f64> {
+        match self.segment(t) {
+            Some((i, a, b, _)) => self.m[i] * a + self.m[i + 1] * b,
+            None => Vector3::zero(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::assert_relative_eq;
+
+    #[test]
+    fn test_spline() {
+        let waypoints = [
+            (0.0, vec3(0.0, 0.0, 0.0)),
+            (0.5, vec3(1.0, 0.5, 0.0)),
+            (1.5, vec3(0.0, 1.0, -1.0)),
+            (2.0, vec3(-1.0, 0.0, 2.0)),
+            (3.0, vec3(0.0, 0.0, 0.0)),
+        ];
+        let s = CubicSpline::new(&waypoints).unwrap();
+        for &(t, p) in waypoints.iter() {
+            assert_relative_eq!(s.x(t), p, epsilon = 1e-12);
+        }
+        // natural ends
+        assert_relative_eq!(s.a(0.0), Vector3::zero());
+        assert_relative_eq!(s.a(3.0), Vector3::zero());
+        // C2 at the inner waypoints
+        let eps = 1e-9;
+        for &(t, _) in waypoints[1..4].iter() {
+            assert_relative_eq!(s.v(t - eps), s.v(t + eps), epsilon = 1e-6);
+            assert_relative_eq!(s.a(t - eps), s.a(t + eps), epsilon = 1e-6);
+        }
+        let eps = 1e-5;
+        for t in [0.2, 1.1, 2.7] {
+            assert_relative_eq!(
+                (s.x(t + eps) - s.x(t - eps)) / (2.0 * eps),
+                s.v(t),
+                epsilon = 1e-6
+            );
+            assert_relative_eq!(
+                (s.v(t + eps) - s.v(t - eps)) / (2.0 * eps),
+                s.a(t),
+                epsilon = 1e-6
+            );
+        }
+        assert_eq!(s.x(-1.0), waypoints[0].1);
+        assert_eq!(s.x(4.0), waypoints[4].1);
+    }
+
+    #[test]
+    fn test_spline_few_points() {
+        let s =
+            CubicSpline::new(&[(0.0, vec3(0.0, 0.0, 0.0)), (2.0, vec3(2.0, 0.0, 0.0))]).unwrap();
+        assert_relative_eq!(s.v(1.0), vec3(1.0, 0.0, 0.0));
+        let s = CubicSpline::new(&[
+            (0.0, vec3(0.0, 0.0, 0.0)),
+            (1.0, vec3(1.0, 1.0, 0.0)),
+            (2.0, vec3(2.0, 0.0, 0.0)),
+        ])
+        .unwrap();
+        assert_relative_eq!(s.x(1.0), vec3(1.0, 1.0, 0.0));
+        assert!(CubicSpline::new(&[(0.0, Vector3::zero()), (0.0, Vector3::zero())]).is_err());
+    }
+
+    #[test]
+    fn test_from_csv() {
+        let data = "t,x,y,z\n# comment\n0,0,0,0\n\n1, 1, 2, 3\n2,0,0,0\n";
+        let s = CubicSpline::from_csv(data).unwrap();
+        assert_relative_eq!(s.x(1.0), vec3(1.0, 2.0, 3.0));
+        assert!(CubicSpline::from_csv("0,0,0,0\n1,1,1\n").is_err());
+        assert!(CubicSpline::from_csv("0,0,0,0\n1,a,1,1\n").is_err());
+        // only a header is skipped, not a broken first waypoint
+        assert!(CubicSpline::from_csv("0,0,0,O\n1,0,0,0\n2,0,0,0\n").is_err());
+        assert!(CubicSpline::from_csv("t,x,y,z\nt,x,y,z\n1,0,0,0\n2,0,0,0\n").is_err());
+    }
+}
@@ -0,0 +1,202 @@
+use std::fmt::Debug;
+
+use cgmath::{InnerSpace, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// External force acting on each bob, in addition to the uniform gravity
+///
+/// Everything is in SI units.
+pub trait ForceField: Debug {
+    /// Force on the `i`-th bob of `mass` at `x` moving with `v` at time `t`
+    fn force(&self, i: usize, x: Vector3<f64>, v: Vector3<f64>, t: f64, mass: f64) -> Vector3<f64>;
+
+    /// Potential energy of the `i`-th bob, `None` if the field is not conservative
+    fn potential(&self, _i: usize, _x: Vector3<f64>, _mass: f64) -> Option<f64> {
+        None
+    }
+}
+
+/// Magnet-like attraction toward `center` that does not depend on the mass
+///
+/// `softening` keeps the force finite at the center.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PointAttractor {
+    pub center: Vector3<f64>,
+    pub strength: f64,
+    pub softening: f64,
+}
+
+impl ForceField for PointAttractor {
+    fn force(
+        &self,
+        _i: usize,
+        x: Vector3<f64>,
+        _v: Vector3<f64>,
+        _t: f64,
+        _mass: f64,
+    ) -> Vector3<f64> {
+        let d = self.center - x;
+        let r2 = d.magnitude2() + self.softening * self.softening;
+        d * (self.strength / (r2 * r2.sqrt()))
+    }
+
+    fn potential(&self, _i: usize, x: Vector3<f64>, _mass: f64) -> Option<f64> {
+        let r2 = (self.center - x).magnitude2() + self.softening * self.softening;
+        Some(-self.strength / r2.sqrt())
+    }
+}
+
+/// Newtonian gravity of a point mass at `center`, `gm` is its gravitational parameter
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CentralGravity {
+    pub center: Vector3<f64>,
+    pub gm: f64,
+}
+
+impl ForceField for CentralGravity {
+    fn force(
+        &self,
+        _i: usize,
+        x: Vector3<f64>,
+        _v: Vector3<f64>,
+        _t: f64,
+        mass: f64,
+    ) -> Vector3<f64> {
+        let d = self.center - x;
+        let r2 = d.magnitude2();
+        d * (self.gm * mass / (r2 * r2.sqrt()))
+    }
+
+    fn potential(&self, _i: usize, x: Vector3<f64>, mass: f64) -> Option<f64> {
+        Some(-self.gm * mass / (self.center - x).magnitude())
+    }
+}
+
+/// Uniform electric `field` acting on bobs with `charge[i]`, missing charges are 0
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UniformElectric {
+    pub field: Vector3<f64>,
+    pub charge: Vec<f64>,
+}
+
+impl UniformElectric {
+    fn charge(&self, i: usize) -> f64 {
+        self.charge.get(i).copied().unwrap_or(0.0)
+    }
+}
+
+impl ForceField for UniformElectric {
+    fn force(
+        &self,
+        i: usize,
+        _x: Vector3<f64>,
+        _v: Vector3<f64>,
+        _t: f64,
+        _mass: f64,
+    ) -> Vector3<f64> {
+        self.field * self.charge(i)
+    }
+
+    fn potential(&self, i: usize, x: Vector3<f64>, _mass: f64) -> Option<f64> {
+        Some(-self.charge(i) * self.field.dot(x))
+    }
+}
+
+/// Spring of `stiffness` pulling every bob toward `center`
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HarmonicWell {
+    pub center: Vector3<f64>,
+    pub stiffness: f64,
+}
+
+impl ForceField for HarmonicWell {
+    fn force(
+        &self,
+        _i: usize,
+        x: Vector3<f64>,
+        _v: Vector3<f64>,
+        _t: f64,
+        _mass: f64,
+    ) -> Vector3<f64> {
+        (self.center - x) * self.stiffness
+    }
+
+    fn potential(&self, _i: usize, x: Vector3<f64>, _mass: f64) -> Option<f64> {
+        Some(0.5 * self.stiffness * (x - self.center).magnitude2())
+    }
+}
+
+/// Drag proportional to the velocity, e.g. to let a pendulum settle
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LinearDrag {
+    pub coefficient: f64,
+}
+
+impl ForceField for LinearDrag {
+    fn force(
+        &self,
+        _i: usize,
+        _x: Vector3<f64>,
+        v: Vector3<f64>,
+        _t: f64,
+        _mass: f64,
+    ) -> Vector3<f64> {
+        -v * self.coefficient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{assert_relative_eq, vec3, Zero};
+
+    /// force must be -grad of the potential
+    fn assert_conservative<F: ForceField>(field: &F, x: Vector3<f64>) {
+        let eps = 1e-6;
+        let p = |x| field.potential(1, x, 2.0).unwrap();
+        let grad = vec3(
+            p(x + vec3(eps, 0.0, 0.0)) - p(x - vec3(eps, 0.0, 0.0)),
+            p(x + vec3(0.0, eps, 0.0)) - p(x - vec3(0.0, eps, 0.0)),
+            p(x + vec3(0.0, 0.0, eps)) - p(x - vec3(0.0, 0.0, eps)),
+        ) / (2.0 * eps);
+        assert_relative_eq!(
+            field.force(1, x, Vector3::zero(), 0.0, 2.0),
+            -grad,
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn test_conservative() {
+        let x = vec3(0.3, -0.2, 0.5);
+        assert_conservative(
+            &PointAttractor {
+                center: vec3(0.0, -1.0, 0.0),
+                strength: 0.5,
+                softening: 0.1,
+            },
+            x,
+        );
+        assert_conservative(
+            &CentralGravity {
+                center: vec3(1.0, 1.0, 0.0),
+                gm: 3.0,
+            },
+            x,
+        );
+        assert_conservative(
+            &UniformElectric {
+                field: vec3(1.0, 2.0, 0.0),
+                charge: vec![0.5, -0.3],
+            },
+            x,
+        );
+        assert_conservative(
+            &HarmonicWell {
+                center: vec3(0.0, 0.5, 0.0),
+                stiffness: 4.0,
+            },
+            x,
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use eom_sim::{runge_kutta::RK4, Eom, Explicit};
+use serde::{Deserialize, Serialize};
+
+use crate::pendulum::Pendulum;
+
+/// Time integration scheme selectable at runtime
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Integrator {
+    /// Forward Euler, gains energy quickly
+    Euler,
+    /// Semi-implicit Euler, updates the velocity first
+    SymplecticEuler,
+    /// Heun's method, second order
+    Heun,
+    /// Classical Runge-Kutta of `eom_sim`
+    #[default]
+    Rk4,
+    /// `Rk4` followed by putting the bobs back on the link lengths every step
+    ProjectedRk4,
+}
+
+impl Integrator {
+    pub const ALL: [Integrator; 5] = [
+        Integrator::Euler,
+        Integrator::SymplecticEuler,
+        Integrator::Heun,
+        Integrator::Rk4,
+        Integrator::ProjectedRk4,
+    ];
+
+    pub fn from_name(name: &str) -> Result<Integrator, String> {
+        Integrator::ALL
+            .into_iter()
+            .find(|i| i.name() == name)
+            .ok_or_else(|| format!("unknown integrator '{}'", name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Integrator::Euler => "euler",
+            Integrator::SymplecticEuler => "symplectic-euler",
+            Integrator::Heun => "heun",
+            Integrator::Rk4 => "rk4",
+            Integrator::ProjectedRk4 => "projected-rk4",
+        }
+    }
+}
+
+/// Anything `Pendulum` can be integrated with
+pub(crate) trait Stepper {
+    fn iterate_until(
+        &mut self,
+        eom: &Pendulum,
+        t: &mut f64,
+        x: &mut [f64],
+        v: &mut [f64],
+        dt: f64,
+        until: f64,
+    );
+}
+
+impl<E: Explicit<Pendulum>> Stepper for E {
+    fn iterate_until(
+        &mut self,
+        eom: &Pendulum,
+        t: &mut f64,
+        x: &mut [f64],
+        v: &mut [f64],
+        dt: f64,
+        until: f64,
+    ) {
+        Explicit::iterate_until(self, eom, t, x, v, dt, until)
+    }
+}
+
+impl Stepper for Integrator {
+    fn iterate_until(
+        &mut self,
+        eom: &Pendulum,
+        t: &mut f64,
+        x: &mut [f64],
+        v: &mut [f64],
+        dt: f64,
+        until: f64,
+    ) {
+        match self {
+            Integrator::Euler => iterate_until(&mut Euler::default(), eom, t, x, v, dt, until),
+            Integrator::SymplecticEuler => {
+                iterate_until(&mut SymplecticEuler::default(), eom, t, x, v, dt, until)
+            }
+            Integrator::Heun => iterate_until(&mut Heun::default(), eom, t, x, v, dt, until),
+            Integrator::Rk4 => Explicit::iterate_until(&mut RK4::new(), eom, t, x, v, dt, until),
+            Integrator::ProjectedRk4 => {
+                iterate_until(&mut Projected(RK4::new()), eom, t, x, v, dt, until)
+            }
+        }
+    }
+}
+
+/// A scheme of `Integrator` that `eom_sim` does not have
+trait Scheme {
+    /// Advance `x` and `v` from `t` by `h`
+    fn step(&mut self, eom: &Pendulum, t: f64, x: &mut [f64], v: &mut [f64], h: f64);
+}
+
+/// As `Explicit::iterate_until` does, correcting the state after every step
+fn iterate_until<S: Scheme>(
+    scheme: &mut S,
+    eom: &Pendulum,
+    t: &mut f64,
+    x: &mut [f64],
+    v: &mut [f64],
+    dt: f64,
+    until: f64,
+) {
+    while *t < until {
+        let h = dt.min(until - *t);
+        scheme.step(eom, *t, x, v, h);
+        *t += h;
+        eom.correct(*t, x, v);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Euler {
+    a: Vec<f64>,
+}
+
+impl Scheme for Euler {
+    fn step(&mut self, eom: &Pendulum, t: f64, x: &mut [f64], v: &mut [f64], h: f64) {
+        self.a.resize(x.len(), 0.0);
+        eom.acceleration(t, x, v, &mut self.a);
+        for i in 0..x.len() {
+            x[i] += v[i] * h;
+            v[i] += self.a[i] * h;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct SymplecticEuler {
+    a: Vec<f64>,
+}
+
+impl Scheme for SymplecticEuler {
+    fn step(&mut self, eom: &Pendulum, t: f64, x: &mut [f64], v: &mut [f64], h: f64) {
+        self.a.resize(x.len(), 0.0);
+        eom.acceleration(t, x, v, &mut self.a);
+        for i in 0..x.len() {
+            v[i] += self.a[i] * h;
+            x[i] += v[i] * h;
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Heun {
+    a: Vec<f64>,
+    /// Euler step to the end of the interval
+    x1: Vec<f64>,
+    v1: Vec<f64>,
+    a1: Vec<f64>,
+}
+
+impl Scheme for Heun {
+    fn step(&mut self, eom: &Pendulum, t: f64, x: &mut [f64], v: &mut [f64], h: f64) {
+        let n = x.len();
+        self.a.resize(n, 0.0);
+        self.a1.resize(n, 0.0);
+        eom.acceleration(t, x, v, &mut self.a);
+        self.x1.clear();
+        self.x1
+            .extend(x.iter().zip(v.iter()).map(|(x, v)| x + v * h));
+        self.v1.clear();
+        self.v1
+            .extend(v.iter().zip(self.a.iter()).map(|(v, a)| v + a * h));
+        eom.acceleration(t + h, &self.x1, &self.v1, &mut self.a1);
+        for i in 0..n {
+            x[i] += (v[i] + self.v1[i]) * (0.5 * h);
+            v[i] += (self.a[i] + self.a1[i]) * (0.5 * h);
+        }
+    }
+}
+
+/// A step of the inner scheme followed by putting the bobs back on the link lengths
+#[derive(Debug)]
+struct Projected<E>(E);
+
+impl<E: Explicit<Pendulum>> Scheme for Projected<E> {
+    fn step(&mut self, eom: &Pendulum, t: f64, x: &mut [f64], v: &mut [f64], h: f64) {
+        let mut s = t;
+        Explicit::iterate_until(&mut self.0, eom, &mut s, x, v, h, t + h);
+        eom.project(t + h, x, v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dynamics::FixedPoint;
+    use cgmath::{vec3, InnerSpace, MetricSpace, Vector3, Zero};
+
+    #[test]
+    fn test_name() {
+        for integrator in Integrator::ALL {
+            assert_eq!(Integrator::from_name(integrator.name()), Ok(integrator));
+        }
+        assert!(Integrator::from_name("leapfrog").is_err());
+        assert_eq!(
+            serde_json::to_string(&Integrator::ProjectedRk4).unwrap(),
+            "\"projected-rk4\""
+        );
+    }
+
+    #[test]
+    fn test_energy() {
+        let mut pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.3, 1.0), (0.3, 1.0)]).unwrap();
+        pendulum.set_root(FixedPoint(Vector3::zero()));
+        let start = pendulum
+            .pose_from_angles(Vector3::zero(), vec3(1.0, 0.0, 0.0), &[0.5, 0.5])
+            .unwrap();
+        let e0 = pendulum.potential_energy(&start);
+        for integrator in Integrator::ALL {
+            let mut position = start.clone();
+            let mut velocity = vec![Vector3::zero(); 2];
+            pendulum.advance_with(
+                integrator,
+                None,
+                0.0,
+                5.0,
+                1e-3,
+                &mut position,
+                &mut velocity,
+            );
+            let error =
+                pendulum.potential_energy(&position) + pendulum.kinetic_energy(&velocity) - e0;
+            let tolerance = match integrator {
+                Integrator::Euler | Integrator::SymplecticEuler => 0.1,
+                Integrator::Heun => 1e-5,
+                Integrator::Rk4 | Integrator::ProjectedRk4 => 1e-7,
+            };
+            assert!(error.abs() < tolerance, "{:?} {}", integrator, error);
+            if integrator == Integrator::ProjectedRk4 {
+                assert!((position[0].magnitude() - 0.3).abs() < 1e-12);
+                assert!((position[0].distance(position[1]) - 0.3).abs() < 1e-12);
+            }
+        }
+    }
+}
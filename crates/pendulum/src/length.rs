@@ -0,0 +1,116 @@
+use std::fmt::Debug;
+
+use serde::{Deserialize, Serialize};
+
+/// Length of a link as a function of time, with its derivatives
+pub trait LinkLength: Debug {
+    fn l(&self, t: f64) -> f64;
+
+    fn dl(&self, t: f64) -> f64;
+
+    fn ddl(&self, t: f64) -> f64;
+}
+
+/// Constant length
+impl LinkLength for f64 {
+    fn l(&self, _t: f64) -> f64 {
+        *self
+    }
+
+    fn dl(&self, _t: f64) -> f64 {
+        0.0
+    }
+
+    fn ddl(&self, _t: f64) -> f64 {
+        0.0
+    }
+}
+
+/// Reeled out at a constant `rate` (negative to reel in), but never shorter than `min`
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Winch {
+    pub length: f64,
+    pub rate: f64,
+    pub min: f64,
+}
+
+impl LinkLength for Winch {
+    fn l(&self, t: f64) -> f64 {
+        (self.length + self.rate * t).max(self.min)
+    }
+
+    fn dl(&self, t: f64) -> f64 {
+        if self.length + self.rate * t > self.min {
+            self.rate
+        } else {
+            0.0
+        }
+    }
+
+    fn ddl(&self, _t: f64) -> f64 {
+        0.0
+    }
+}
+
+/// `mean + amplitude * sin(omega t + phase)`, e.g. a child pumping a swing
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pumping {
+    pub mean: f64,
+    pub amplitude: f64,
+    pub omega: f64,
+    #[serde(default)]
+    pub phase: f64,
+}
+
+impl LinkLength for Pumping {
+    fn l(&self, t: f64) -> f64 {
+        self.mean + self.amplitude * (self.omega * t + self.phase).sin()
+    }
+
+    fn dl(&self, t: f64) -> f64 {
+        self.amplitude * self.omega * (self.omega * t + self.phase).cos()
+    }
+
+    fn ddl(&self, t: f64) -> f64 {
+        -self.amplitude * self.omega * self.omega * (self.omega * t + self.phase).sin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::assert_relative_eq;
+
+    #[test]
+    fn test_derivatives() {
+        let winch = Winch {
+            length: 1.0,
+            rate: -0.5,
+            min: 0.2,
+        };
+        assert_relative_eq!(winch.l(1.0), 0.5);
+        assert_relative_eq!(winch.dl(1.0), -0.5);
+        assert_relative_eq!(winch.l(2.0), 0.2);
+        assert_relative_eq!(winch.dl(2.0), 0.0);
+
+        let pumping = Pumping {
+            mean: 1.0,
+            amplitude: 0.1,
+            omega: 6.0,
+            phase: 0.3,
+        };
+        let eps = 1e-5;
+        for t in [0.0, 0.4, 1.7] {
+            assert_relative_eq!(
+                (pumping.l(t + eps) - pumping.l(t - eps)) / (2.0 * eps),
+                pumping.dl(t),
+                epsilon = 1e-8
+            );
+            assert_relative_eq!(
+                (pumping.dl(t + eps) - pumping.dl(t - eps)) / (2.0 * eps),
+                pumping.ddl(t),
+                epsilon = 1e-6
+            );
+        }
+    }
+}
@@ -1,4 +1,19 @@
 pub mod dynamics;
+mod field;
+mod integrator;
+mod length;
 mod pendulum;
+mod recorder;
+mod snapshot;
 
-pub use crate::{dynamics::*, pendulum::Pendulum};
+pub use crate::{
+    dynamics::*,
+    field::{
+        CentralGravity, ForceField, HarmonicWell, LinearDrag, PointAttractor, UniformElectric,
+    },
+    integrator::Integrator,
+    length::{LinkLength, Pumping, Winch},
+    pendulum::{LinkKind, Pendulum},
+    recorder::{Recorder, Sample},
+    snapshot::Snapshot,
+};
@@ -1,19 +1,52 @@
-use cgmath::{vec3, InnerSpace, Vector3};
+use cgmath::{vec3, InnerSpace, Vector3, Zero};
 use eom_sim::{Eom, Explicit, ModelSpec};
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
-use crate::dynamics::{Bezier4, Dynamics};
+use crate::{
+    dynamics::{Bezier4, Dynamics, Hermite3},
+    field::ForceField,
+    integrator::{Integrator, Stepper},
+    length::LinkLength,
+    recorder::Recorder,
+};
+
+/// Slack is allowed while a string is shorter than this fraction of its length
+const SLACK_TOLERANCE: f64 = 1e-6;
+
+/// How a link keeps the distance between its ends
+#[derive(Debug, Copy, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LinkKind {
+    /// Keeps the length exactly
+    #[default]
+    Rod,
+    /// Can only pull, goes slack when compressed and jerks taut again
+    /// with `restitution` in [0, 1], 0 for inelastic and 1 for elastic
+    String { restitution: f64 },
+}
 
 #[derive(Debug)]
 pub struct Pendulum {
+    // parameters as given to `new`
+    gravity: Vector3<f64>,
+    length_mass: Vec<(f64, f64)>,
     g: Vector3<f64>,
-    // length: Vec<f64>,
+    length: Vec<Box<dyn LinkLength>>,
+    // some lengths are set by `set_link_length`
+    prescribed_length: bool,
+    kind: Vec<LinkKind>,
     mass: Vec<f64>,
     unit_time: f64,
     unit_length: f64,
     unit_mass: f64,
-    root: Bezier4,
+    root: Box<dyn Dynamics>,
+    // the root is driven by `set_root` rather than flying freely
+    custom_root: bool,
+    fields: Vec<Box<dyn ForceField>>,
+    // Baumgarte stabilization of the length constraints
+    stabilization: f64,
 }
 
 impl Pendulum {
@@ -21,6 +54,9 @@ impl Pendulum {
         if length_mass.len() <= 1 {
             return Err(String::from("N must be grater than 1"));
         }
+        if g.magnitude2() == 0.0 {
+            return Err(String::from("g must not be zero"));
+        }
         let mut unit_length = f64::INFINITY;
         let mut unit_mass = 0.0;
         for (l, m) in length_mass {
@@ -33,32 +69,143 @@ impl Pendulum {
             unit_length = unit_length.min(*l);
             unit_mass += *m;
         }
-        // let length = length_mass
-        //     .iter()
-        //     .map(|(l, _)| *l / unit_length)
-        //     .collect::<Vec<_>>();
+        let length = length_mass
+            .iter()
+            .map(|(l, _)| Box::new(*l / unit_length) as Box<dyn LinkLength>)
+            .collect::<Vec<_>>();
         let mass = length_mass
             .iter()
             .map(|(_, m)| *m / unit_mass)
             .collect::<Vec<_>>();
         let unit_time = (unit_length / g.magnitude()).sqrt();
         Ok(Pendulum {
+            gravity: g,
+            length_mass: length_mass.to_vec(),
             g: g.normalize(),
-            // length,
+            length,
+            prescribed_length: false,
+            kind: vec![LinkKind::Rod; length_mass.len()],
             mass,
             unit_length,
             unit_time,
             unit_mass,
-            root: Bezier4::default(),
+            root: Box::new(Bezier4::default()),
+            custom_root: false,
+            fields: Vec::new(),
+            stabilization: 0.0,
         })
     }
 
+    pub fn gravity(&self) -> Vector3<f64> {
+        self.gravity
+    }
+
+    /// Change the gravitational acceleration at `time` [s], keeping the link kinds,
+    /// the stabilization, the force fields and the free root
+    ///
+    /// The units of the pendulum follow the gravity, so the prescribed lengths and the root
+    /// driver, given in the old units, are refused.
+    pub fn set_gravity(&mut self, time: f64, g: Vector3<f64>) -> Result<(), String> {
+        if self.prescribed_length || self.custom_root {
+            return Err(String::from(
+                "the gravity of a pendulum with prescribed lengths or a root driver cannot be changed",
+            ));
+        }
+        let mut pendulum = Pendulum::new(g, &self.length_mass)?;
+        pendulum.kind.clone_from(&self.kind);
+        pendulum.set_stabilization(self.stabilization());
+        pendulum.set_free_root(time, self.root_position(time), self.root_velocity(time));
+        pendulum.fields = std::mem::take(&mut self.fields);
+        *self = pendulum;
+        Ok(())
+    }
+
+    pub fn length_mass(&self) -> &[(f64, f64)] {
+        &self.length_mass
+    }
+
+    /// Add an external force field acting on every bob
+    pub fn add_field<F: ForceField + 'static>(&mut self, field: F) {
+        self.fields.push(Box::new(field));
+    }
+
+    pub fn clear_fields(&mut self) {
+        self.fields.clear();
+    }
+
+    /// Prescribe the length of the `i`-th link (in SI units) from now on
+    pub fn set_link_length<L: LinkLength + 'static>(
+        &mut self,
+        i: usize,
+        length: L,
+    ) -> Result<(), String> {
+        if i >= self.length.len() {
+            return Err(format!("no link {}", i));
+        }
+        self.length[i] = Box::new(ScaledLength {
+            inner: length,
+            unit_time: self.unit_time,
+            unit_length: self.unit_length,
+        });
+        self.prescribed_length = true;
+        Ok(())
+    }
+
+    /// Has only the parameters of `new`, `set_link_kind` and `set_stabilization`,
+    /// and the root given by `tick` or `set_free_root`
+    ///
+    /// Force fields, prescribed lengths and root drivers are not plain data,
+    /// so a `Snapshot` cannot keep them.
+    pub fn is_plain(&self) -> bool {
+        self.fields.is_empty() && !self.prescribed_length && !self.custom_root
+    }
+
+    pub fn set_link_kind(&mut self, i: usize, kind: LinkKind) -> Result<(), String> {
+        if i >= self.kind.len() {
+            return Err(format!("no link {}", i));
+        }
+        if let LinkKind::String { restitution } = kind {
+            if !(0.0..=1.0).contains(&restitution) {
+                return Err(String::from("restitution must be in [0, 1]"));
+            }
+        }
+        self.kind[i] = kind;
+        Ok(())
+    }
+
+    pub fn link_kind(&self, i: usize) -> LinkKind {
+        self.kind[i]
+    }
+
+    pub fn link_length(&self, i: usize, time: f64) -> f64 {
+        self.length[i].l(time / self.unit_time) * self.unit_length
+    }
+
+    /// Pull drifted lengths back with the critically damped `rate` [1/s], 0 to disable
+    ///
+    /// Useful when the lengths vary, since the integration error is not corrected otherwise.
+    pub fn set_stabilization(&mut self, rate: f64) {
+        self.stabilization = rate * self.unit_time;
+    }
+
+    /// Rate given to `set_stabilization` [1/s]
+    pub fn stabilization(&self) -> f64 {
+        self.stabilization / self.unit_time
+    }
+
+    /// Potential energy of the gravity and the conservative force fields
     pub fn potential_energy(&self, x: &[Vector3<f64>]) -> f64 {
         let mut e = 0.0;
         for (&m, x) in self.mass.iter().zip(x) {
             e += m * x.dot(self.g);
         }
-        e * self.unit_mass * self.unit_length / self.unit_time / self.unit_time
+        let mut e = e * self.unit_mass * self.unit_length / self.unit_time / self.unit_time;
+        for field in self.fields.iter() {
+            for (i, (&m, x)) in self.mass.iter().zip(x).enumerate() {
+                e += field.potential(i, *x, m * self.unit_mass).unwrap_or(0.0);
+            }
+        }
+        e
     }
 
     pub fn kinetic_energy(&self, v: &[Vector3<f64>]) -> f64 {
@@ -82,46 +229,128 @@ impl Pendulum {
         v: &[Vector3<f64>],
     ) -> Vec<Vector3<f64>> {
         let n = x.len();
+        let e = self.field_acceleration(t, x, v);
 
-        let x = {
-            let mut xx = Vec::with_capacity(n);
-            xx.push(x[0] - self.root.x(t));
-            for (a, b) in x.iter().tuple_windows() {
-                xx.push(b - a);
-            }
-            xx
-        };
-        let v = {
-            let mut vv = Vec::with_capacity(n);
-            vv.push(v[0] - self.root.v(t));
-            for (a, b) in v.iter().tuple_windows() {
-                vv.push(b - a);
+        let (x, v) = self.relative(t, x, v);
+
+        let mut c = Vec::with_capacity(n);
+        c.push(v[0].magnitude2() - x[0].dot(self.g + self.root.a(t) - e[0]));
+        for ((v, x), (ea, eb)) in v.iter().zip(x.iter()).skip(1).zip(e.iter().tuple_windows()) {
+            c.push(v.magnitude2() + x.dot(eb - ea));
+        }
+        // d^2/dt^2 (|x|^2 - l^2) / 2 = 0 with the moving length l(t)
+        let alpha = self.stabilization;
+        for ((c, length), (x, v)) in c
+            .iter_mut()
+            .zip(self.length.iter())
+            .zip(x.iter().zip(v.iter()))
+        {
+            let (l, dl) = (length.l(t), length.dl(t));
+            *c -= dl * dl + l * length.ddl(t);
+            if alpha > 0.0 {
+                let error = 0.5 * (x.magnitude2() - l * l);
+                let rate = x.dot(*v) - l * dl;
+                *c += 2.0 * alpha * rate + alpha * alpha * error;
             }
-            vv
-        };
+        }
+
+        // strings go slack instead of pushing, so release them until all of them pull
+        let mut active = self
+            .kind
+            .iter()
+            .zip(self.length.iter())
+            .zip(x.iter().zip(v.iter()))
+            .map(|((kind, length), (x, v))| match kind {
+                LinkKind::Rod => true,
+                LinkKind::String { .. } => {
+                    let l = length.l(t);
+                    // taut, and not getting shorter
+                    x.magnitude() >= l * (1.0 - SLACK_TOLERANCE)
+                        && x.dot(*v) - l * length.dl(t) >= -SLACK_TOLERANCE
+                }
+            })
+            .collect::<Vec<_>>();
+        let lambda = self.solve_pulling(&x, &mut active, &c);
+
+        let mut a = self.link_forces(&x, &lambda);
+        for (a, e) in a.iter_mut().zip(e) {
+            *a += e - self.g;
+        }
+        a
+    }
+
+    /// Link vectors and their velocities, starting from the root
+    fn relative(
+        &self,
+        t: f64,
+        x: &[Vector3<f64>],
+        v: &[Vector3<f64>],
+    ) -> (Vec<Vector3<f64>>, Vec<Vector3<f64>>) {
+        let mut xx = Vec::with_capacity(x.len());
+        xx.push(x[0] - self.root.x(t));
+        for (a, b) in x.iter().tuple_windows() {
+            xx.push(b - a);
+        }
+        let mut vv = Vec::with_capacity(v.len());
+        vv.push(v[0] - self.root.v(t));
+        for (a, b) in v.iter().tuple_windows() {
+            vv.push(b - a);
+        }
+        (xx, vv)
+    }
 
-        let lambda = {
-            let mut a = Vec::with_capacity(n);
-            a.push(x[0].magnitude2() / self.mass[0]);
-            for (x, (&ma, &mb)) in x.iter().skip(1).zip(self.mass.iter().tuple_windows()) {
-                a.push(x.magnitude2() * (ma + mb) / (ma * mb));
+    /// Solve the multipliers of the links for the right hand side `c`, inactive links get 0
+    ///
+    /// The same system gives the tensions for the accelerations
+    /// and the impulses for the velocities.
+    fn solve_links(&self, x: &[Vector3<f64>], active: &[bool], c: &[f64]) -> Vec<f64> {
+        let n = x.len();
+        let mut a = Vec::with_capacity(n);
+        a.push(x[0].magnitude2() / self.mass[0]);
+        for (x, (&ma, &mb)) in x.iter().skip(1).zip(self.mass.iter().tuple_windows()) {
+            a.push(x.magnitude2() * (ma + mb) / (ma * mb));
+        }
+        let mut b = Vec::with_capacity(n - 1);
+        for ((xa, xb), &m) in x.iter().tuple_windows().zip(self.mass.iter()) {
+            b.push(xa.dot(*xb) / m);
+        }
+        let mut c = c.to_vec();
+        for (i, _) in active.iter().enumerate().filter(|(_, &active)| !active) {
+            a[i] = 1.0;
+            c[i] = 0.0;
+            if i > 0 {
+                b[i - 1] = 0.0;
             }
-            let mut b = Vec::with_capacity(n - 1);
-            for ((xa, xb), &m) in x.iter().tuple_windows().zip(self.mass.iter()) {
-                b.push(xa.dot(*xb) / m);
+            if i + 1 < n {
+                b[i] = 0.0;
             }
-            let mut c = Vec::with_capacity(n);
-            c.push(v[0].magnitude2() - x[0].dot(self.g + self.root.a(t)));
-            for v in v.iter().skip(1) {
-                c.push(v.magnitude2());
+        }
+        debug_assert_eq!(a.len(), n);
+        debug_assert_eq!(b.len(), n - 1);
+        debug_assert_eq!(c.len(), n);
+        thomas(&a, &b, &c)
+    }
+
+    /// Same as `solve_links`, and release the strings which would push
+    fn solve_pulling(&self, x: &[Vector3<f64>], active: &mut [bool], c: &[f64]) -> Vec<f64> {
+        loop {
+            let lambda = self.solve_links(x, active, c);
+            let mut released = false;
+            for ((active, kind), &l) in active.iter_mut().zip(self.kind.iter()).zip(lambda.iter()) {
+                if *active && l < 0.0 && *kind != LinkKind::Rod {
+                    *active = false;
+                    released = true;
+                }
             }
-            debug_assert_eq!(a.len(), n);
-            debug_assert_eq!(b.len(), n - 1);
-            debug_assert_eq!(c.len(), n);
-            thomas(&a, &b, &c)
-        };
-        debug_assert_eq!(lambda.len(), n);
+            if !released {
+                return lambda;
+            }
+        }
+    }
 
+    /// Acceleration of each bob by the link multipliers `lambda`
+    fn link_forces(&self, x: &[Vector3<f64>], lambda: &[f64]) -> Vec<Vector3<f64>> {
+        let n = x.len();
         let mut a = Vec::with_capacity(n);
         for ((&m, (xa, xb)), (&la, &lb)) in self
             .mass
@@ -129,13 +358,94 @@ impl Pendulum {
             .zip(x.iter().tuple_windows())
             .zip(lambda.iter().tuple_windows())
         {
-            a.push((xb * lb - xa * la) / m - self.g);
+            a.push((xb * lb - xa * la) / m);
         }
-        a.push(-x[n - 1] * lambda[n - 1] / self.mass[n - 1] - self.g);
-        debug_assert_eq!(a.len(), n);
+        a.push(-x[n - 1] * lambda[n - 1] / self.mass[n - 1]);
         a
     }
 
+    /// Pull strings stretched beyond their lengths back, and jerk them taut
+    /// if they are still stretching
+    fn jerk_strings(&self, t: f64, position: &mut [Vector3<f64>], velocity: &mut [Vector3<f64>]) {
+        let n = position.len();
+        let mut jerk = vec![None; n];
+        let mut last = self.root.x(t);
+        for i in 0..n {
+            if let LinkKind::String { restitution } = self.kind[i] {
+                let d = position[i] - last;
+                let l = self.length[i].l(t);
+                if d.magnitude() > l {
+                    // move the whole chain below, so the other links keep their lengths
+                    let shift = d.normalize_to(l) - d;
+                    for p in position[i..].iter_mut() {
+                        *p += shift;
+                    }
+                    jerk[i] = Some(restitution);
+                }
+            }
+            last = position[i];
+        }
+        if jerk.iter().all(Option::is_none) {
+            return;
+        }
+
+        // impulses J with x_i.dv_i = -(A J)_i, which turn the stretching rate u into -e u
+        let (x, v) = self.relative(t, position, velocity);
+        let mut active = vec![false; n];
+        let mut c = vec![0.0; n];
+        let mut jerking = false;
+        for i in 0..n {
+            let u = x[i].dot(v[i]) - self.length[i].l(t) * self.length[i].dl(t);
+            match (self.kind[i], jerk[i]) {
+                (LinkKind::Rod, _) => {
+                    active[i] = true;
+                    c[i] = u;
+                }
+                (_, Some(e)) if u > 0.0 => {
+                    active[i] = true;
+                    c[i] = (1.0 + e) * u;
+                    jerking = true;
+                }
+                _ => (),
+            }
+        }
+        if !jerking {
+            return;
+        }
+        let impulse = self.solve_links(&x, &active, &c);
+        for (v, dv) in velocity.iter_mut().zip(self.link_forces(&x, &impulse)) {
+            *v += dv;
+        }
+    }
+
+    /// Acceleration of each bob by the force fields in the dimensionless units
+    fn field_acceleration(
+        &self,
+        t: f64,
+        x: &[Vector3<f64>],
+        v: &[Vector3<f64>],
+    ) -> Vec<Vector3<f64>> {
+        let mut e = vec![Vector3::zero(); x.len()];
+        if self.fields.is_empty() {
+            return e;
+        }
+        let unit_velocity = self.unit_length / self.unit_time;
+        let unit_force = self.unit_mass * self.unit_length / self.unit_time / self.unit_time;
+        for field in self.fields.iter() {
+            for (i, ((x, v), &m)) in x.iter().zip(v).zip(self.mass.iter()).enumerate() {
+                let f = field.force(
+                    i,
+                    x * self.unit_length,
+                    v * unit_velocity,
+                    t * self.unit_time,
+                    m * self.unit_mass,
+                );
+                e[i] += f / (unit_force * m);
+            }
+        }
+        e
+    }
+
     pub fn tick<E: Explicit<Pendulum>>(
         &mut self,
         ticker: &mut E,
@@ -147,13 +457,345 @@ impl Pendulum {
         position: &mut [Vector3<f64>],
         velocity: &mut [Vector3<f64>],
     ) -> (f64, Vector3<f64>, Vector3<f64>) {
-        let mut t = time_start / self.unit_time;
+        self.tick_impl(
+            ticker,
+            None,
+            time_start,
+            time_end,
+            root_start,
+            root_velocity_start,
+            root_end,
+            position,
+            velocity,
+        )
+    }
+
+    /// Same as `tick`, and feeds `recorder` with the samples falling in the interval
+    // the parameters of `tick` plus the recorder
+    #[allow(clippy::too_many_arguments)]
+    pub fn tick_recorded<E: Explicit<Pendulum>>(
+        &mut self,
+        ticker: &mut E,
+        recorder: &mut Recorder,
+        time_start: f64,
+        time_end: f64,
+        root_start: Vector3<f64>,
+        root_velocity_start: Vector3<f64>,
+        root_end: Vector3<f64>,
+        position: &mut [Vector3<f64>],
+        velocity: &mut [Vector3<f64>],
+    ) -> (f64, Vector3<f64>, Vector3<f64>) {
+        self.tick_impl(
+            ticker,
+            Some(recorder),
+            time_start,
+            time_end,
+            root_start,
+            root_velocity_start,
+            root_end,
+            position,
+            velocity,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn tick_impl<E: Explicit<Pendulum>>(
+        &mut self,
+        ticker: &mut E,
+        recorder: Option<&mut Recorder>,
+        time_start: f64,
+        time_end: f64,
+        root_start: Vector3<f64>,
+        root_velocity_start: Vector3<f64>,
+        root_end: Vector3<f64>,
+        position: &mut [Vector3<f64>],
+        velocity: &mut [Vector3<f64>],
+    ) -> (f64, Vector3<f64>, Vector3<f64>) {
+        let t = time_start / self.unit_time;
         let until = time_end / self.unit_time;
 
         if time_end <= time_start {
             return (time_start, root_start, root_velocity_start);
         }
+
+        self.custom_root = false;
+        self.root = Box::new(Bezier4::from_2points(
+            root_start / self.unit_length,
+            root_velocity_start * self.unit_time / self.unit_length,
+            root_end / self.unit_length,
+            t,
+            until,
+        ));
+
+        let dt = (until - t) / 2.0f64.powi(10);
+        let t = self.integrate(ticker, recorder, t, until, dt, position, velocity);
+        (
+            t * self.unit_time,
+            self.root.x(t) * self.unit_length,
+            self.root.v(t) * self.unit_length / self.unit_time,
+        )
+    }
+
+    /// Drive the root along `root` (in SI units) from now on
+    pub fn set_root<D: Dynamics + 'static>(&mut self, root: D) {
+        self.root = Box::new(Scaled {
+            inner: root,
+            unit_time: self.unit_time,
+            unit_length: self.unit_length,
+        });
+        self.custom_root = true;
+    }
+
+    /// Let the root fly on from `position` with the constant `velocity` at `time` (in SI units)
+    ///
+    /// Unlike `set_root`, this keeps the pendulum `is_plain`.
+    pub fn set_free_root(&mut self, time: f64, position: Vector3<f64>, velocity: Vector3<f64>) {
+        self.set_root(Hermite3::new(
+            position,
+            velocity,
+            position + velocity,
+            velocity,
+            time,
+            time + 1.0,
+        ));
+        self.custom_root = false;
+    }
+
+    pub fn root_position(&self, time: f64) -> Vector3<f64> {
+        self.root.x(time / self.unit_time) * self.unit_length
+    }
+
+    pub fn root_velocity(&self, time: f64) -> Vector3<f64> {
+        self.root.v(time / self.unit_time) * self.unit_length / self.unit_time
+    }
+
+    /// Integrate from `time_start` to `time_end` by steps of at most `dt`,
+    /// with the root given by `set_root`
+    ///
+    /// Returns the time actually reached.
+    // an interval, a step and the state, as `tick` takes them
+    #[allow(clippy::too_many_arguments)]
+    pub fn advance<E: Explicit<Pendulum>>(
+        &mut self,
+        ticker: &mut E,
+        recorder: Option<&mut Recorder>,
+        time_start: f64,
+        time_end: f64,
+        dt: f64,
+        position: &mut [Vector3<f64>],
+        velocity: &mut [Vector3<f64>],
+    ) -> f64 {
+        self.advance_impl(
+            ticker, recorder, time_start, time_end, dt, position, velocity,
+        )
+    }
+
+    /// Same as `advance` with an `Integrator` chosen at runtime
+    #[allow(clippy::too_many_arguments)]
+    pub fn advance_with(
+        &mut self,
+        mut integrator: Integrator,
+        recorder: Option<&mut Recorder>,
+        time_start: f64,
+        time_end: f64,
+        dt: f64,
+        position: &mut [Vector3<f64>],
+        velocity: &mut [Vector3<f64>],
+    ) -> f64 {
+        self.advance_impl(
+            &mut integrator,
+            recorder,
+            time_start,
+            time_end,
+            dt,
+            position,
+            velocity,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn advance_impl<S: Stepper>(
+        &mut self,
+        ticker: &mut S,
+        recorder: Option<&mut Recorder>,
+        time_start: f64,
+        time_end: f64,
+        dt: f64,
+        position: &mut [Vector3<f64>],
+        velocity: &mut [Vector3<f64>],
+    ) -> f64 {
+        if time_end <= time_start {
+            return time_start;
+        }
+        let t = self.integrate(
+            ticker,
+            recorder,
+            time_start / self.unit_time,
+            time_end / self.unit_time,
+            dt / self.unit_time,
+            position,
+            velocity,
+        );
+        t * self.unit_time
+    }
+
+    /// Hit the `bob` with `impulse` [N s] at `time`
+    ///
+    /// The links pass the impulse along so that they keep their lengths,
+    /// except taut strings pushed to go slack.
+    pub fn apply_impulse(
+        &self,
+        time: f64,
+        bob: usize,
+        impulse: Vector3<f64>,
+        position: &[Vector3<f64>],
+        velocity: &mut [Vector3<f64>],
+    ) -> Result<(), String> {
+        let n = self.mass.len();
+        if position.len() != n || velocity.len() != n {
+            return Err(format!("expect {} bobs", n));
+        }
+        if bob >= n {
+            return Err(format!("no bob {}", bob));
+        }
+        let t = time / self.unit_time;
+        let (x, v) = self.to_internal(position, velocity);
+        let x = as_vec3(&x);
+        let mut v = as_vec3(&v);
+        v[bob] += impulse / (self.mass[bob] * self.unit_mass) * self.unit_time / self.unit_length;
+        self.constrain_velocity(t, &x, &mut v);
+        for (velocity, v) in velocity.iter_mut().zip(v) {
+            *velocity = v * self.unit_length / self.unit_time;
+        }
+        Ok(())
+    }
+
+    /// Cancel the stretching rates of the rods and taut strings with impulses along the links
+    fn constrain_velocity(&self, t: f64, position: &[Vector3<f64>], velocity: &mut [Vector3<f64>]) {
+        let n = position.len();
+        // impulses J with x_i.dv_i = -(A J)_i, cancelling the stretching rate u
+        let (x, v) = self.relative(t, position, velocity);
+        let mut active = vec![true; n];
+        let mut c = vec![0.0; n];
+        for i in 0..n {
+            let l = self.length[i].l(t);
+            c[i] = x[i].dot(v[i]) - l * self.length[i].dl(t);
+            if self.kind[i] != LinkKind::Rod {
+                active[i] = x[i].magnitude() >= l * (1.0 - SLACK_TOLERANCE) && c[i] > 0.0;
+            }
+        }
+        let impulse = self.solve_pulling(&x, &mut active, &c);
+        for (v, dv) in velocity.iter_mut().zip(self.link_forces(&x, &impulse)) {
+            *v += dv;
+        }
+    }
+
+    /// Put the bobs back on the link lengths and cancel the stretching velocities,
+    /// in the flat dimensionless arrays
+    ///
+    /// Slack strings are left as they are.
+    pub(crate) fn project(&self, t: f64, x: &mut [f64], v: &mut [f64]) {
+        let mut position = as_vec3(x);
+        let mut velocity = as_vec3(v);
+        let mut last = self.root.x(t);
+        for i in 0..position.len() {
+            let d = position[i] - last;
+            let l = self.length[i].l(t);
+            if self.kind[i] == LinkKind::Rod || d.magnitude() > l {
+                // move the whole chain below, so the other links keep their lengths
+                let shift = d.normalize_to(l) - d;
+                for p in position[i..].iter_mut() {
+                    *p += shift;
+                }
+            }
+            last = position[i];
+        }
+        self.constrain_velocity(t, &position, &mut velocity);
+        write_flat(&position, x);
+        write_flat(&velocity, v);
+    }
+
+    /// Chain hanging from `root` in the plane spanned by gravity and `axis`
+    ///
+    /// `angles[i]` is the angle of i-th link from the downward direction toward `axis`.
+    /// The links have their lengths at time 0.
+    pub fn pose_from_angles(
+        &self,
+        root: Vector3<f64>,
+        axis: Vector3<f64>,
+        angles: &[f64],
+    ) -> Result<Vec<Vector3<f64>>, String> {
+        if angles.len() != self.length_mass.len() {
+            return Err(format!(
+                "expect {} angles, but got {}",
+                self.length_mass.len(),
+                angles.len()
+            ));
+        }
+        let side = axis - self.g * axis.dot(self.g);
+        if side.magnitude2() == 0.0 {
+            return Err(String::from("axis must not be parallel to gravity"));
+        }
+        let side = side.normalize();
+        let mut position = Vec::with_capacity(angles.len());
+        let mut last = root;
+        for (i, &theta) in angles.iter().enumerate() {
+            let (sin, cos) = theta.sin_cos();
+            last += (-self.g * cos + side * sin) * self.link_length(i, 0.0);
+            position.push(last);
+        }
+        Ok(position)
+    }
+
+    /// Integrate in the dimensionless units, returns the time reached
+    #[allow(clippy::too_many_arguments)]
+    fn integrate<S: Stepper>(
+        &self,
+        ticker: &mut S,
+        recorder: Option<&mut Recorder>,
+        mut t: f64,
+        until: f64,
+        dt: f64,
+        position: &mut [Vector3<f64>],
+        velocity: &mut [Vector3<f64>],
+    ) -> f64 {
         assert_eq!(position.len(), velocity.len());
+        let (mut x, mut v) = self.to_internal(position, velocity);
+
+        match recorder {
+            None => ticker.iterate_until(self, &mut t, &mut x, &mut v, dt, until),
+            Some(recorder) => loop {
+                if recorder.is_due(t * self.unit_time) {
+                    self.write_internal(&x, &v, position, velocity);
+                    recorder.record(
+                        self,
+                        t * self.unit_time,
+                        self.root.x(t) * self.unit_length,
+                        self.root.v(t) * self.unit_length / self.unit_time,
+                        position,
+                        velocity,
+                    );
+                }
+                if t >= until {
+                    break;
+                }
+                // stop exactly at the next sample with steps no longer than `dt`
+                let stop = (recorder.next_time() / self.unit_time).min(until);
+                let h = (stop - t) / ((stop - t) / dt).ceil().max(1.0);
+                ticker.iterate_until(self, &mut t, &mut x, &mut v, h, stop);
+            },
+        }
+
+        self.write_internal(&x, &v, position, velocity);
+        t
+    }
+
+    /// Convert to the dimensionless flat arrays integrated by `Eom`
+    fn to_internal(
+        &self,
+        position: &[Vector3<f64>],
+        velocity: &[Vector3<f64>],
+    ) -> (Vec<f64>, Vec<f64>) {
         let n = position.len() * 3;
         let mut x = Vec::with_capacity(n);
         let mut v = Vec::with_capacity(n);
@@ -167,18 +809,16 @@ impl Pendulum {
             v.push(p.y * self.unit_time / self.unit_length);
             v.push(p.z * self.unit_time / self.unit_length);
         }
+        (x, v)
+    }
 
-        self.root = Bezier4::from_2points(
-            root_start / self.unit_length,
-            root_velocity_start * self.unit_time / self.unit_length,
-            root_end / self.unit_length,
-            t,
-            until,
-        );
-
-        let dt = (until - t) / 2.0f64.powi(10);
-        ticker.iterate_until(self, &mut t, &mut x, &mut v, dt, until);
-
+    fn write_internal(
+        &self,
+        x: &[f64],
+        v: &[f64],
+        position: &mut [Vector3<f64>],
+        velocity: &mut [Vector3<f64>],
+    ) {
         for (i, p) in position.iter_mut().enumerate() {
             let i = i * 3;
             p.x = x[i] * self.unit_length;
@@ -191,12 +831,6 @@ impl Pendulum {
             p.y = v[i + 1] * (self.unit_length / self.unit_time);
             p.z = v[i + 2] * (self.unit_length / self.unit_time);
         }
-
-        (
-            t * self.unit_time,
-            self.root.x(t) * self.unit_length,
-            self.root.v(t) * self.unit_length / self.unit_time,
-        )
     }
 }
 
@@ -206,33 +840,79 @@ impl ModelSpec for Pendulum {
 
 impl Eom for Pendulum {
     fn acceleration(&self, t: f64, x: &[f64], v: &[f64], a: &mut [f64]) {
-        fn as_vec3(v: &[f64]) -> Vec<Vector3<f64>> {
-            let mut ret = Vec::with_capacity(v.len() / 3);
-            for i in (0..v.len()).step_by(3) {
-                ret.push(vec3(v[i], v[i + 1], v[i + 2]));
-            }
-            ret
-        }
-
         let a_vec3 = self.calc_acceleration(t, &as_vec3(x), &as_vec3(v));
-        for (i, v) in a_vec3.iter().enumerate() {
-            a[i * 3] = v.x;
-            a[i * 3 + 1] = v.y;
-            a[i * 3 + 2] = v.z;
+        write_flat(&a_vec3, a);
+    }
+
+    fn correct(&self, t: f64, x: &mut [f64], v: &mut [f64]) {
+        if self.kind.iter().all(|&k| k == LinkKind::Rod) {
+            return;
         }
+        let mut position = as_vec3(x);
+        let mut velocity = as_vec3(v);
+        self.jerk_strings(t, &mut position, &mut velocity);
+        write_flat(&position, x);
+        write_flat(&velocity, v);
+    }
+}
+
+fn as_vec3(v: &[f64]) -> Vec<Vector3<f64>> {
+    let mut ret = Vec::with_capacity(v.len() / 3);
+    for i in (0..v.len()).step_by(3) {
+        ret.push(vec3(v[i], v[i + 1], v[i + 2]));
+    }
+    ret
+}
+
+fn write_flat(v: &[Vector3<f64>], out: &mut [f64]) {
+    for (i, v) in v.iter().enumerate() {
+        out[i * 3] = v.x;
+        out[i * 3 + 1] = v.y;
+        out[i * 3 + 2] = v.z;
+    }
+}
+
+/// `Dynamics` in SI units seen in the dimensionless units of `Pendulum`
+#[derive(Debug)]
+struct Scaled<D> {
+    inner: D,
+    unit_time: f64,
+    unit_length: f64,
+}
+
+impl<D: Dynamics> Dynamics for Scaled<D> {
+    fn x(&self, t: f64) -> Vector3<f64> {
+        self.inner.x(t * self.unit_time) / self.unit_length
+    }
+
+    fn v(&self, t: f64) -> Vector3<f64> {
+        self.inner.v(t * self.unit_time) * self.unit_time / self.unit_length
     }
 
-    fn correct(&self, _t: f64, _x: &mut [f64], _v: &mut [f64]) {
-        // let mut last_pos = self.root.x(t * self.unit_time) / self.unit_length;
-        // for (i, &l) in self.length.iter().enumerate() {
-        //     let i = i * 3;
-        //     let pos = vec3(x[i], x[i + 1], x[i + 2]);
-        //     let pos = last_pos + (pos - last_pos).normalize_to(l);
-        //     x[i] = pos.x;
-        //     x[i + 1] = pos.y;
-        //     x[i + 2] = pos.z;
-        //     last_pos = pos;
-        // }
+    fn a(&self, t: f64) -> Vector3<f64> {
+        self.inner.a(t * self.unit_time) * self.unit_time * self.unit_time / self.unit_length
+    }
+}
+
+/// `LinkLength` in SI units seen in the dimensionless units of `Pendulum`
+#[derive(Debug)]
+struct ScaledLength<L> {
+    inner: L,
+    unit_time: f64,
+    unit_length: f64,
+}
+
+impl<L: LinkLength> LinkLength for ScaledLength<L> {
+    fn l(&self, t: f64) -> f64 {
+        self.inner.l(t * self.unit_time) / self.unit_length
+    }
+
+    fn dl(&self, t: f64) -> f64 {
+        self.inner.dl(t * self.unit_time) * self.unit_time / self.unit_length
+    }
+
+    fn ddl(&self, t: f64) -> f64 {
+        self.inner.ddl(t * self.unit_time) * self.unit_time * self.unit_time / self.unit_length
     }
 }
 
@@ -249,7 +929,7 @@ impl Eom for Pendulum {
 /// [  0   0  -b2  a3] [x3]   [c3]
 /// ```
 #[must_use]
-fn thomas(a: &[f64], b: &[f64], c: &[f64]) -> Vec<f64> {
+pub(crate) fn thomas(a: &[f64], b: &[f64], c: &[f64]) -> Vec<f64> {
     let n = a.len();
     let mut d = Vec::with_capacity(n + 1);
     let mut e = Vec::with_capacity(n + 1);
@@ -272,7 +952,13 @@ fn thomas(a: &[f64], b: &[f64], c: &[f64]) -> Vec<f64> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        dynamics::FixedPoint,
+        field::{HarmonicWell, UniformElectric},
+        length::{Pumping, Winch},
+    };
     use cgmath::{assert_relative_eq, vec4, Matrix4, SquareMatrix, Vector4};
+    use eom_sim::runge_kutta::RK4;
 
     #[test]
     fn test_thomas() {
@@ -293,4 +979,208 @@ mod tests {
             epsilon = f64::EPSILON * 4.0,
         );
     }
+
+    #[test]
+    fn test_field_energy() {
+        let mut pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.3, 1.0), (0.2, 0.5)]).unwrap();
+        pendulum.set_root(FixedPoint(Vector3::zero()));
+        pendulum.add_field(HarmonicWell {
+            center: vec3(0.2, -0.5, 0.1),
+            stiffness: 20.0,
+        });
+        pendulum.add_field(UniformElectric {
+            field: vec3(3.0, 0.0, 1.0),
+            charge: vec![0.0, -1.0],
+        });
+        let mut position = pendulum
+            .pose_from_angles(Vector3::zero(), vec3(1.0, 0.0, 0.0), &[1.0, -0.5])
+            .unwrap();
+        let mut velocity = vec![vec3(0.0, 0.0, 0.5), Vector3::zero()];
+        let energy = |p: &Pendulum, x: &[Vector3<f64>], v: &[Vector3<f64>]| {
+            p.potential_energy(x) + p.kinetic_energy(v)
+        };
+        let e0 = energy(&pendulum, &position, &velocity);
+        pendulum.advance(
+            &mut RK4::new(),
+            None,
+            0.0,
+            2.0,
+            1e-4,
+            &mut position,
+            &mut velocity,
+        );
+        assert_relative_eq!(energy(&pendulum, &position, &velocity), e0, epsilon = 1e-6);
+        assert!(velocity[0].z.abs() > 0.0);
+
+        pendulum.clear_fields();
+        assert_relative_eq!(
+            pendulum.potential_energy(&position),
+            9.8 * (position[0].y + 0.5 * position[1].y),
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_variable_length() {
+        let mut pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.5, 1.0), (0.3, 1.0)]).unwrap();
+        pendulum.set_root(FixedPoint(Vector3::zero()));
+        pendulum
+            .set_link_length(
+                0,
+                Winch {
+                    length: 0.5,
+                    rate: -0.1,
+                    min: 0.2,
+                },
+            )
+            .unwrap();
+        pendulum
+            .set_link_length(
+                1,
+                Pumping {
+                    mean: 0.3,
+                    amplitude: 0.05,
+                    omega: 5.0,
+                    phase: 0.0,
+                },
+            )
+            .unwrap();
+        assert!(pendulum.set_link_length(2, 1.0).is_err());
+        pendulum.set_stabilization(10.0);
+
+        let mut position = pendulum
+            .pose_from_angles(Vector3::zero(), vec3(1.0, 0.0, 0.0), &[0.5, 0.2])
+            .unwrap();
+        // start on the constraint velocity: bob 1 moves along link 1 as it grows
+        let mut velocity = vec![
+            position[0].normalize() * -0.1,
+            position[0].normalize() * -0.1 + (position[1] - position[0]).normalize() * 0.25,
+        ];
+        let t = pendulum.advance(
+            &mut RK4::new(),
+            None,
+            0.0,
+            2.0,
+            1e-3,
+            &mut position,
+            &mut velocity,
+        );
+        assert_relative_eq!(t, 2.0);
+        assert_relative_eq!(pendulum.link_length(0, t), 0.3, epsilon = 1e-12);
+        assert_relative_eq!(position[0].magnitude(), 0.3, epsilon = 1e-6);
+        assert_relative_eq!(
+            (position[1] - position[0]).magnitude(),
+            pendulum.link_length(1, t),
+            epsilon = 1e-6
+        );
+    }
+
+    #[test]
+    fn test_string() {
+        for restitution in [0.0, 1.0] {
+            let mut pendulum =
+                Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.3, 1.0), (0.3, 0.5)]).unwrap();
+            pendulum.set_root(FixedPoint(Vector3::zero()));
+            pendulum
+                .set_link_kind(1, LinkKind::String { restitution })
+                .unwrap();
+            assert!(pendulum
+                .set_link_kind(1, LinkKind::String { restitution: 2.0 })
+                .is_err());
+
+            // throw the lower bob up past the upper one
+            let mut position = vec![vec3(0.0, -0.3, 0.0), vec3(0.0, -0.6, 0.0)];
+            let mut velocity = vec![Vector3::zero(), vec3(0.3, 2.5, 0.0)];
+            let energy = |p: &Pendulum, x: &[Vector3<f64>], v: &[Vector3<f64>]| {
+                p.potential_energy(x) + p.kinetic_energy(v)
+            };
+            let e0 = energy(&pendulum, &position, &velocity);
+            let mut shortest = f64::INFINITY;
+            let mut t = 0.0;
+            for _ in 0..100 {
+                t = pendulum.advance(
+                    &mut RK4::new(),
+                    None,
+                    t,
+                    t + 0.01,
+                    1e-4,
+                    &mut position,
+                    &mut velocity,
+                );
+                let l = (position[1] - position[0]).magnitude();
+                assert!(l <= 0.3 + 1e-9, "{}", l);
+                assert_relative_eq!(position[0].magnitude(), 0.3, epsilon = 1e-6);
+                shortest = shortest.min(l);
+            }
+            assert!(shortest < 0.2, "{}", shortest);
+            let e1 = energy(&pendulum, &position, &velocity);
+            if restitution == 1.0 {
+                assert_relative_eq!(e1, e0, max_relative = 1e-3);
+            } else {
+                assert!(e1 < e0 - 0.01, "{} {}", e0, e1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_set_gravity() {
+        let mut pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.3, 1.0), (0.2, 2.0)]).unwrap();
+        pendulum
+            .set_link_kind(1, LinkKind::String { restitution: 0.5 })
+            .unwrap();
+        pendulum.set_stabilization(3.0);
+        pendulum.set_free_root(1.0, vec3(0.1, 0.2, 0.0), vec3(0.0, 0.0, 0.5));
+        pendulum.set_gravity(1.5, vec3(0.0, 1.6, 0.0)).unwrap();
+        assert_eq!(pendulum.gravity(), vec3(0.0, 1.6, 0.0));
+        assert_eq!(pendulum.length_mass(), &[(0.3, 1.0), (0.2, 2.0)]);
+        assert_eq!(pendulum.link_kind(1), LinkKind::String { restitution: 0.5 });
+        assert_relative_eq!(pendulum.stabilization(), 3.0, epsilon = 1e-12);
+        assert!(pendulum.is_plain());
+        assert_relative_eq!(
+            pendulum.root_position(2.0),
+            vec3(0.1, 0.2, 0.5),
+            epsilon = 1e-12
+        );
+        assert!(pendulum.set_gravity(1.5, Vector3::zero()).is_err());
+
+        pendulum.set_root(FixedPoint(Vector3::zero()));
+        assert!(pendulum.set_gravity(1.5, vec3(0.0, 9.8, 0.0)).is_err());
+    }
+
+    #[test]
+    fn test_apply_impulse() {
+        let mut pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.3, 1.0), (0.2, 2.0)]).unwrap();
+        pendulum.set_root(FixedPoint(Vector3::zero()));
+        let position = pendulum
+            .pose_from_angles(Vector3::zero(), vec3(1.0, 0.0, 0.0), &[0.3, 1.2])
+            .unwrap();
+        let mut velocity = vec![Vector3::zero(); 2];
+        let impulse = vec3(0.5, 0.2, -0.3);
+        pendulum
+            .apply_impulse(0.0, 1, impulse, &position, &mut velocity)
+            .unwrap();
+        assert!(pendulum
+            .apply_impulse(0.0, 2, impulse, &position, &mut velocity)
+            .is_err());
+
+        // links do not stretch
+        assert_relative_eq!(position[0].dot(velocity[0]), 0.0, epsilon = 1e-12);
+        let d = position[1] - position[0];
+        assert_relative_eq!(d.dot(velocity[1] - velocity[0]), 0.0, epsilon = 1e-12);
+        // out of the plane nothing but the hit bob moves
+        assert_relative_eq!(velocity[0].z, 0.0, epsilon = 1e-12);
+        assert_relative_eq!(velocity[1].z, -0.15, epsilon = 1e-12);
+        assert!(velocity[0].magnitude() > 0.0);
+
+        // a string pushed toward its root goes slack and passes nothing
+        pendulum
+            .set_link_kind(1, LinkKind::String { restitution: 0.0 })
+            .unwrap();
+        let mut velocity = vec![Vector3::zero(); 2];
+        pendulum
+            .apply_impulse(0.0, 1, -d, &position, &mut velocity)
+            .unwrap();
+        assert_relative_eq!(velocity[0], Vector3::zero());
+        assert_relative_eq!(velocity[1], -d / 2.0, epsilon = 1e-12);
+    }
 }
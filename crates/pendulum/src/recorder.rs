@@ -0,0 +1,233 @@
+use std::io::{self, Write};
+
+use cgmath::Vector3;
+
+use crate::pendulum::Pendulum;
+
+const BINARY_MAGIC: &[u8; 4] = b"MPTR";
+const BINARY_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sample {
+    pub time: f64,
+    pub root_position: Vector3<f64>,
+    pub root_velocity: Vector3<f64>,
+    pub position: Vec<Vector3<f64>>,
+    pub velocity: Vec<Vector3<f64>>,
+    pub kinetic_energy: f64,
+    pub potential_energy: f64,
+}
+
+/// Trajectory sampled every `interval` seconds
+///
+/// Feed it with `Pendulum::tick_recorded`, or call `record` directly.
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    interval: f64,
+    length_mass: Vec<(f64, f64)>,
+    next_time: Option<f64>,
+    samples: Vec<Sample>,
+}
+
+impl Recorder {
+    pub fn new(pendulum: &Pendulum, interval: f64) -> Result<Recorder, String> {
+        if interval <= 0.0 {
+            return Err(String::from("interval must be positive"));
+        }
+        Ok(Recorder {
+            interval,
+            length_mass: pendulum.length_mass().to_vec(),
+            next_time: None,
+            samples: Vec::new(),
+        })
+    }
+
+    pub fn interval(&self) -> f64 {
+        self.interval
+    }
+
+    pub fn samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    pub fn clear(&mut self) {
+        self.next_time = None;
+        self.samples.clear();
+    }
+
+    /// Time of the next sample, `-inf` before the first one
+    pub fn next_time(&self) -> f64 {
+        self.next_time.unwrap_or(f64::NEG_INFINITY)
+    }
+
+    pub fn is_due(&self, time: f64) -> bool {
+        time + self.interval * 1e-9 >= self.next_time()
+    }
+
+    /// Append a sample and schedule the next one `interval` later
+    pub fn record(
+        &mut self,
+        pendulum: &Pendulum,
+        time: f64,
+        root_position: Vector3<f64>,
+        root_velocity: Vector3<f64>,
+        position: &[Vector3<f64>],
+        velocity: &[Vector3<f64>],
+    ) {
+        assert_eq!(position.len(), self.length_mass.len());
+        assert_eq!(velocity.len(), self.length_mass.len());
+        self.samples.push(Sample {
+            time,
+            root_position,
+            root_velocity,
+            position: position.to_vec(),
+            velocity: velocity.to_vec(),
+            kinetic_energy: pendulum.kinetic_energy(velocity),
+            potential_energy: pendulum.potential_energy(position),
+        });
+        self.next_time = Some(match self.next_time {
+            Some(next) if time < next + self.interval => next + self.interval,
+            _ => time + self.interval,
+        });
+    }
+
+    /// Write samples as CSV with a header line
+    ///
+    /// Columns are `t`, root position and velocity, positions of all bobs,
+    /// velocities of all bobs, kinetic and potential energy.
+    pub fn write_csv<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let mut header = vec![String::from("t")];
+        for name in [
+            "root_x", "root_y", "root_z", "root_vx", "root_vy", "root_vz",
+        ] {
+            header.push(String::from(name));
+        }
+        for prefix in ["", "v"] {
+            for i in 0..self.length_mass.len() {
+                for axis in ["x", "y", "z"] {
+                    header.push(format!("{}{}{}", prefix, axis, i));
+                }
+            }
+        }
+        header.push(String::from("kinetic_energy"));
+        header.push(String::from("potential_energy"));
+        writeln!(w, "{}", header.join(","))?;
+
+        for sample in self.samples.iter() {
+            let row = sample_values(sample)
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>();
+            writeln!(w, "{}", row.join(","))?;
+        }
+        Ok(())
+    }
+
+    /// Write samples in little-endian binary
+    ///
+    /// ```text
+    /// b"MPTR", version: u32, N: u32,
+    /// mass: [f64; N], length: [f64; N],
+    /// number of samples: u64,
+    /// samples: [[f64; 6N + 9]; number of samples]
+    /// ```
+    ///
+    /// Each sample has the same values in the same order as a row of `write_csv`.
+    pub fn write_binary<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(BINARY_MAGIC)?;
+        w.write_all(&BINARY_VERSION.to_le_bytes())?;
+        w.write_all(&(self.length_mass.len() as u32).to_le_bytes())?;
+        for &(_, m) in self.length_mass.iter() {
+            w.write_all(&m.to_le_bytes())?;
+        }
+        for &(l, _) in self.length_mass.iter() {
+            w.write_all(&l.to_le_bytes())?;
+        }
+        w.write_all(&(self.samples.len() as u64).to_le_bytes())?;
+        for sample in self.samples.iter() {
+            for v in sample_values(sample) {
+                w.write_all(&v.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn sample_values(sample: &Sample) -> Vec<f64> {
+    let mut values = Vec::with_capacity(sample.position.len() * 6 + 9);
+    values.push(sample.time);
+    for v in [sample.root_position, sample.root_velocity] {
+        values.extend_from_slice(&[v.x, v.y, v.z]);
+    }
+    for v in sample.position.iter().chain(sample.velocity.iter()) {
+        values.extend_from_slice(&[v.x, v.y, v.z]);
+    }
+    values.push(sample.kinetic_energy);
+    values.push(sample.potential_energy);
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{vec3, Zero};
+    use eom_sim::runge_kutta::RK4;
+
+    #[test]
+    fn test_record_while_tick() {
+        let mut pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.3, 1.0), (0.3, 1.0)]).unwrap();
+        let mut recorder = Recorder::new(&pendulum, 0.25).unwrap();
+        let mut position = vec![vec3(0.3, 0.0, 0.0), vec3(0.6, 0.0, 0.0)];
+        let mut velocity = vec![Vector3::zero(); 2];
+        let root = Vector3::zero();
+        for i in 0..4 {
+            pendulum.tick_recorded(
+                &mut RK4::new(),
+                &mut recorder,
+                i as f64 * 0.3,
+                (i + 1) as f64 * 0.3,
+                root,
+                root,
+                root,
+                &mut position,
+                &mut velocity,
+            );
+        }
+        let times = recorder
+            .samples()
+            .iter()
+            .map(|s| s.time)
+            .collect::<Vec<_>>();
+        assert_eq!(times.len(), 5);
+        for (i, t) in times.into_iter().enumerate() {
+            assert!((t - i as f64 * 0.25).abs() < 1e-9, "{} {}", i, t);
+        }
+    }
+
+    #[test]
+    fn test_write() {
+        let pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.3, 1.0), (0.2, 2.0)]).unwrap();
+        let mut recorder = Recorder::new(&pendulum, 0.1).unwrap();
+        let position = [vec3(0.0, -0.3, 0.0), vec3(0.0, -0.5, 0.0)];
+        let velocity = [vec3(1.0, 0.0, 0.0), vec3(2.0, 0.0, 0.0)];
+        let zero = Vector3::zero();
+        recorder.record(&pendulum, 0.0, zero, zero, &position, &velocity);
+        recorder.record(&pendulum, 0.1, zero, zero, &position, &velocity);
+
+        let mut csv = Vec::new();
+        recorder.write_csv(&mut csv).unwrap();
+        let csv = String::from_utf8(csv).unwrap();
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("t,root_x,"));
+        assert!(lines[0].ends_with(",vz1,kinetic_energy,potential_energy"));
+        assert_eq!(lines[1].split(',').count(), 2 * 6 + 9);
+
+        let mut bin = Vec::new();
+        recorder.write_binary(&mut bin).unwrap();
+        assert_eq!(&bin[0..4], b"MPTR");
+        assert_eq!(bin.len(), 4 + 4 + 4 + 8 * 4 + 8 + 2 * 8 * (2 * 6 + 9));
+        assert_eq!(u32::from_le_bytes(bin[8..12].try_into().unwrap()), 2);
+        assert_eq!(f64::from_le_bytes(bin[20..28].try_into().unwrap()), 2.0);
+    }
+}
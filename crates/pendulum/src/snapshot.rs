@@ -0,0 +1,251 @@
+use cgmath::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::pendulum::{LinkKind, Pendulum};
+
+/// Complete state of a simulation at some time
+///
+/// The root is driven by its position and velocity, as `Pendulum::tick` does.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub g: Vector3<f64>,
+    pub length_mass: Vec<(f64, f64)>,
+    /// kind of each link
+    #[serde(with = "link_kinds")]
+    pub kind: Vec<LinkKind>,
+    /// rate of `Pendulum::set_stabilization` [1/s]
+    pub stabilization: f64,
+    pub time: f64,
+    pub root_position: Vector3<f64>,
+    pub root_velocity: Vector3<f64>,
+    pub position: Vec<Vector3<f64>>,
+    pub velocity: Vec<Vector3<f64>>,
+}
+
+impl Snapshot {
+    /// Fails if `pendulum` is not `Pendulum::is_plain`, which the snapshot could not rebuild
+    pub fn new(
+        pendulum: &Pendulum,
+        time: f64,
+        root_position: Vector3<f64>,
+        root_velocity: Vector3<f64>,
+        position: &[Vector3<f64>],
+        velocity: &[Vector3<f64>],
+    ) -> Result<Snapshot, String> {
+        if !pendulum.is_plain() {
+            return Err(String::from(
+                "a pendulum with force fields or prescribed lengths cannot be saved",
+            ));
+        }
+        let n = pendulum.length_mass().len();
+        Ok(Snapshot {
+            g: pendulum.gravity(),
+            length_mass: pendulum.length_mass().to_vec(),
+            kind: (0..n).map(|i| pendulum.link_kind(i)).collect(),
+            stabilization: pendulum.stabilization(),
+            time,
+            root_position,
+            root_velocity,
+            position: position.to_vec(),
+            velocity: velocity.to_vec(),
+        })
+    }
+
+    /// Build the `Pendulum` this snapshot was taken from
+    ///
+    /// Its root flies on from `root_position` with `root_velocity`, see `Pendulum::set_free_root`.
+    pub fn pendulum(&self) -> Result<Pendulum, String> {
+        self.validate()?;
+        let mut pendulum = Pendulum::new(self.g, &self.length_mass)?;
+        for (i, &kind) in self.kind.iter().enumerate() {
+            pendulum.set_link_kind(i, kind)?;
+        }
+        pendulum.set_stabilization(self.stabilization);
+        pendulum.set_free_root(self.time, self.root_position, self.root_velocity);
+        Ok(pendulum)
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|err| err.to_string())
+    }
+
+    pub fn from_json(data: &str) -> Result<Snapshot, String> {
+        let snapshot: Snapshot = serde_json::from_str(data).map_err(|err| err.to_string())?;
+        snapshot.validate()?;
+        Ok(snapshot)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        bincode::serialize(self).map_err(|err| err.to_string())
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Snapshot, String> {
+        let snapshot: Snapshot = bincode::deserialize(data).map_err(|err| err.to_string())?;
+        snapshot.validate()?;
+        Ok(snapshot)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let n = self.length_mass.len();
+        if self.kind.len() != n {
+            return Err(format!(
+                "expect {} link kinds, but got {}",
+                n,
+                self.kind.len()
+            ));
+        }
+        if self.position.len() != n || self.velocity.len() != n {
+            return Err(format!(
+                "expect {} bobs, but position has {} and velocity has {}",
+                n,
+                self.position.len(),
+                self.velocity.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// `LinkKind` is internally tagged for the scenarios, which bincode cannot read,
+/// so a snapshot keeps the restitution of each string link and `None` for a rod
+mod link_kinds {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::pendulum::LinkKind;
+
+    pub fn serialize<S: Serializer>(kind: &[LinkKind], serializer: S) -> Result<S::Ok, S::Error> {
+        kind.iter()
+            .map(|k| match *k {
+                LinkKind::Rod => None,
+                LinkKind::String { restitution } => Some(restitution),
+            })
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<LinkKind>, D::Error> {
+        let restitution = Vec::<Option<f64>>::deserialize(deserializer)?;
+        Ok(restitution
+            .into_iter()
+            .map(|r| match r {
+                None => LinkKind::Rod,
+                Some(restitution) => LinkKind::String { restitution },
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dynamics::FixedPoint, field::LinearDrag, length::Winch};
+    use cgmath::{assert_relative_eq, vec3};
+
+    fn sample() -> Snapshot {
+        let mut pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.3, 1.0), (0.2, 0.7)]).unwrap();
+        pendulum
+            .set_link_kind(1, LinkKind::String { restitution: 0.5 })
+            .unwrap();
+        pendulum.set_stabilization(4.0);
+        Snapshot::new(
+            &pendulum,
+            1.25,
+            vec3(0.1, 0.0, -0.2),
+            vec3(0.0, 0.3, 0.0),
+            &[vec3(0.1, -0.3, -0.2), vec3(0.3, -0.3, -0.2)],
+            &[vec3(0.5, 0.0, 0.1), vec3(-0.1, 1.0 / 3.0, 0.0)],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let snapshot = sample();
+        let json = snapshot.to_json().unwrap();
+        assert_eq!(Snapshot::from_json(&json).unwrap(), snapshot);
+        let bytes = snapshot.to_bytes().unwrap();
+        assert_eq!(Snapshot::from_bytes(&bytes).unwrap(), snapshot);
+
+        let pendulum = snapshot.pendulum().unwrap();
+        assert_eq!(pendulum.gravity(), snapshot.g);
+        assert_eq!(pendulum.length_mass(), &snapshot.length_mass[..]);
+        assert_eq!(pendulum.link_kind(0), LinkKind::Rod);
+        assert_eq!(pendulum.link_kind(1), LinkKind::String { restitution: 0.5 });
+        assert!((pendulum.stabilization() - 4.0).abs() < 1e-12);
+        // the root flies on from where it was
+        let t = snapshot.time;
+        assert!(pendulum.is_plain());
+        assert_relative_eq!(pendulum.root_position(t), snapshot.root_position);
+        assert_relative_eq!(pendulum.root_velocity(t), snapshot.root_velocity);
+        assert_relative_eq!(
+            pendulum.root_position(t + 2.0),
+            snapshot.root_position + snapshot.root_velocity * 2.0,
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_not_plain() {
+        let x = [vec3(0.0, -0.3, 0.0), vec3(0.0, -0.5, 0.0)];
+        let v = [vec3(0.0, 0.0, 0.0); 2];
+        let root = vec3(0.0, 0.0, 0.0);
+        let mut pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.3, 1.0), (0.2, 0.5)]).unwrap();
+        pendulum.add_field(LinearDrag { coefficient: 0.1 });
+        assert!(Snapshot::new(&pendulum, 0.0, root, root, &x, &v).is_err());
+        pendulum.clear_fields();
+        assert!(Snapshot::new(&pendulum, 0.0, root, root, &x, &v).is_ok());
+        pendulum.set_root(FixedPoint(root));
+        assert!(Snapshot::new(&pendulum, 0.0, root, root, &x, &v).is_err());
+        pendulum.set_free_root(0.0, root, root);
+        assert!(Snapshot::new(&pendulum, 0.0, root, root, &x, &v).is_ok());
+        let winch = Winch {
+            length: 0.3,
+            rate: 0.1,
+            min: 0.1,
+        };
+        pendulum.set_link_length(0, winch).unwrap();
+        assert!(Snapshot::new(&pendulum, 0.0, root, root, &x, &v).is_err());
+    }
+
+    #[test]
+    fn test_roundtrip_exact() {
+        // values with all the bits of the mantissa in use
+        let mut x = 0.0_f64;
+        let mut next = || {
+            x = (x + std::f64::consts::E).fract() * 1.0e3 / 7.0 + 1.0 / 3.0;
+            x
+        };
+        let pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &[(0.3, 1.0); 8]).unwrap();
+        let position = (0..8)
+            .map(|_| vec3(next(), -next(), next() * 1e-5))
+            .collect::<Vec<_>>();
+        let velocity = (0..8)
+            .map(|_| vec3(-next() * 1e7, next(), next()))
+            .collect::<Vec<_>>();
+        let snapshot = Snapshot::new(
+            &pendulum,
+            next(),
+            vec3(next(), next(), next()),
+            vec3(next(), next(), next()),
+            &position,
+            &velocity,
+        )
+        .unwrap();
+        let json = snapshot.to_json().unwrap();
+        let restored = Snapshot::from_json(&json).unwrap();
+        assert!(restored.time == snapshot.time);
+        assert!(restored.position == snapshot.position);
+        assert!(restored.velocity == snapshot.velocity);
+        assert!(restored == snapshot);
+    }
+
+    #[test]
+    fn test_mismatched_state() {
+        let mut snapshot = sample();
+        snapshot.velocity.pop();
+        let json = snapshot.to_json().unwrap();
+        assert!(Snapshot::from_json(&json).is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! Simulations checked against analytic results
+
+use std::f64::consts::{PI, SQRT_2};
+
+use cgmath::{assert_relative_eq, vec3, InnerSpace, MetricSpace, Vector3, Zero};
+use eom_sim::runge_kutta::RK4;
+use pendulum::{Dynamics, FixedPoint, Hermite3, Pendulum};
+
+const G: f64 = 9.8;
+
+fn pendulum(length_mass: &[(f64, f64)]) -> Pendulum {
+    let mut pendulum = Pendulum::new(vec3(0.0, G, 0.0), length_mass).unwrap();
+    pendulum.set_root(FixedPoint(Vector3::zero()));
+    pendulum
+}
+
+fn energy(pendulum: &Pendulum, position: &[Vector3<f64>], velocity: &[Vector3<f64>]) -> f64 {
+    pendulum.potential_energy(position) + pendulum.kinetic_energy(velocity)
+}
+
+/// Mean period of `x[0].x` measured from its downward zero crossings
+fn measure_period(pendulum: &mut Pendulum, angles: &[f64], duration: f64, dt: f64) -> f64 {
+    let axis = vec3(1.0, 0.0, 0.0);
+    let mut position = pendulum
+        .pose_from_angles(Vector3::zero(), axis, angles)
+        .unwrap();
+    let mut velocity = vec![Vector3::zero(); angles.len()];
+    let mut crossings = Vec::new();
+    let mut t = 0.0;
+    let mut last = position[0].x;
+    while t < duration {
+        t = pendulum.advance(
+            &mut RK4::new(),
+            None,
+            t,
+            t + dt,
+            dt,
+            &mut position,
+            &mut velocity,
+        );
+        let now = position[0].x;
+        if last > 0.0 && now <= 0.0 {
+            crossings.push(t - dt * now / (now - last));
+        }
+        last = now;
+    }
+    assert!(crossings.len() >= 2, "{:?}", crossings);
+    (crossings[crossings.len() - 1] - crossings[0]) / (crossings.len() - 1) as f64
+}
+
+/// Slow and fast normal modes of a double pendulum as (ω, θ_2 / θ_1)
+fn double_pendulum_modes(l1: f64, m1: f64, l2: f64, m2: f64) -> [(f64, f64); 2] {
+    // ω^4 (1 - μ) l_1 l_2 - ω^2 g (l_1 + l_2) + g^2 = 0 with μ = m_2 / (m_1 + m_2)
+    let mu = m2 / (m1 + m2);
+    let a = (1.0 - mu) * l1 * l2;
+    let b = G * (l1 + l2);
+    let d = (b * b - 4.0 * a * G * G).sqrt();
+    [b - d, b + d].map(|b| {
+        let omega2 = b / (2.0 * a);
+        (omega2.sqrt(), (G - omega2 * l1) / (omega2 * mu * l2))
+    })
+}
+
+#[test]
+fn test_single_pendulum_period() {
+    // `Pendulum` needs 2 links, so a single pendulum is only approximated by a second bob
+    // too light to disturb the first. The slow mode of this chain is checked exactly,
+    // and it differs from that of the single pendulum by far less than the tolerance.
+    let l = 0.5;
+    let (omega, ratio) = double_pendulum_modes(l, 1.0, l / 4.0, 1e-9)[0];
+    assert_relative_eq!(omega, (G / l).sqrt(), max_relative = 1e-8);
+    let mut pendulum = pendulum(&[(l, 1.0), (l / 4.0, 1e-9)]);
+    let period = measure_period(&mut pendulum, &[1e-3, 1e-3 * ratio], 20.0, 1e-3);
+    assert_relative_eq!(period, 2.0 * PI / omega, max_relative = 1e-5);
+}
+
+#[test]
+fn test_double_pendulum_normal_modes() {
+    // equal masses and lengths: ω^2 = (2 ∓ √2) g / l with θ_2 / θ_1 = ±√2
+    let l = 0.4;
+    for sign in [1.0, -1.0] {
+        let mut pendulum = pendulum(&[(l, 1.0), (l, 1.0)]);
+        let angles = [1e-3, 1e-3 * sign * SQRT_2];
+        let period = measure_period(&mut pendulum, &angles, 20.0, 1e-3);
+        let omega = ((2.0 - sign * SQRT_2) * G / l).sqrt();
+        assert_relative_eq!(period, 2.0 * PI / omega, max_relative = 1e-5);
+    }
+
+    // unequal links and masses, at smaller angles since the fast mode swings the second bob wide
+    let (l1, m1, l2, m2) = (0.5, 1.0, 0.2, 0.3);
+    for (omega, ratio) in double_pendulum_modes(l1, m1, l2, m2) {
+        let mut pendulum = pendulum(&[(l1, m1), (l2, m2)]);
+        let period = measure_period(&mut pendulum, &[1e-4, 1e-4 * ratio], 20.0, 1e-3);
+        assert_relative_eq!(period, 2.0 * PI / omega, max_relative = 1e-5);
+    }
+}
+
+/// Largest relative energy error of a double pendulum checked every `check` periods of its slow mode
+///
+/// Returns the final position as well.
+fn energy_drift(
+    pendulum: &mut Pendulum,
+    steps_per_period: f64,
+    check: f64,
+    count: usize,
+) -> (f64, Vec<Vector3<f64>>) {
+    let l = pendulum.length_mass()[0].0;
+    let mut position = pendulum
+        .pose_from_angles(Vector3::zero(), vec3(1.0, 0.0, 0.0), &[0.5, 0.7])
+        .unwrap();
+    let mut velocity = vec![Vector3::zero(); 2];
+    let e0 = energy(pendulum, &position, &velocity);
+    let period = 2.0 * PI / ((2.0 - SQRT_2) * G / l).sqrt();
+    let dt = period / steps_per_period;
+
+    let mut t = 0.0;
+    let mut max_error = 0.0f64;
+    for _ in 0..count {
+        t = pendulum.advance(
+            &mut RK4::new(),
+            None,
+            t,
+            t + period * check,
+            dt,
+            &mut position,
+            &mut velocity,
+        );
+        max_error = max_error.max((energy(pendulum, &position, &velocity) - e0).abs());
+    }
+    (max_error / e0.abs(), position)
+}
+
+#[test]
+fn test_energy_conservation() {
+    // well beyond small angles, RK4 alone over 10 periods
+    // The error must be small and shrink as dt^4.
+    let l = 0.3;
+    let mut pendulum = pendulum(&[(l, 1.0), (l, 0.5)]);
+    let (coarse, _) = energy_drift(&mut pendulum, 128.0, 1.0, 10);
+    let (fine, _) = energy_drift(&mut pendulum, 256.0, 1.0, 10);
+    assert!(coarse < 2e-4, "{}", coarse);
+    assert_relative_eq!(coarse / fine, 16.0, max_relative = 0.2);
+}
+
+#[test]
+fn test_stabilized_energy_conservation() {
+    // 10^4 periods of the slow mode, where the lengths of the raw integration drift apart
+    let l = 0.3;
+    let mut pendulum = pendulum(&[(l, 1.0), (l, 0.5)]);
+    pendulum.set_stabilization(5.0);
+    let (error, position) = energy_drift(&mut pendulum, 128.0, 100.0, 100);
+    assert!(error < 1e-4, "{}", error);
+    assert_relative_eq!(position[0].magnitude(), l, max_relative = 1e-6);
+    assert_relative_eq!(position[0].distance(position[1]), l, max_relative = 1e-6);
+}
+
+#[test]
+fn test_equilibrium_under_root_acceleration() {
+    // hangs along the effective gravity in the frame of the root
+    let acceleration = vec3(3.0, 1.0, -2.0);
+    let duration = 2.0;
+    let root = Hermite3::new(
+        Vector3::zero(),
+        Vector3::zero(),
+        acceleration * (0.5 * duration * duration),
+        acceleration * duration,
+        0.0,
+        duration,
+    );
+    let mut pendulum =
+        Pendulum::new(vec3(0.0, G, 0.0), &[(0.3, 1.0), (0.2, 2.0), (0.1, 0.5)]).unwrap();
+    pendulum.set_root(root);
+
+    let down = (-vec3(0.0, G, 0.0) - acceleration).normalize();
+    let shape = [down * 0.3, down * 0.5, down * 0.6];
+    let mut position = shape.to_vec();
+    let mut velocity = vec![Vector3::zero(); 3];
+    let t = pendulum.advance(
+        &mut RK4::new(),
+        None,
+        0.0,
+        duration,
+        1e-3,
+        &mut position,
+        &mut velocity,
+    );
+    assert_relative_eq!(t, duration);
+    for (p, s) in position.iter().zip(shape.iter()) {
+        assert_relative_eq!(p - root.x(t), *s, epsilon = 1e-8);
+    }
+    for v in velocity.iter() {
+        assert_relative_eq!(*v, root.v(t), epsilon = 1e-8);
+    }
+}
+
+#[test]
+fn test_time_reversal() {
+    let mut pendulum = pendulum(&[(0.3, 1.0), (0.2, 0.5), (0.25, 0.8)]);
+    let start = pendulum
+        .pose_from_angles(Vector3::zero(), vec3(1.0, 0.0, 1.0), &[0.6, -0.3, 0.4])
+        .unwrap();
+    // a null impulse projects the velocities onto the constraints
+    let mut start_velocity = vec![vec3(0.0, 0.0, 0.3), Vector3::zero(), vec3(0.0, 0.0, -0.2)];
+    pendulum
+        .apply_impulse(0.0, 0, Vector3::zero(), &start, &mut start_velocity)
+        .unwrap();
+    let mut position = start.clone();
+    let mut velocity = start_velocity.clone();
+
+    let t = pendulum.advance(
+        &mut RK4::new(),
+        None,
+        0.0,
+        1.0,
+        1e-4,
+        &mut position,
+        &mut velocity,
+    );
+    let moved = position
+        .iter()
+        .zip(start.iter())
+        .map(|(p, s)| p.distance(*s))
+        .sum::<f64>();
+    assert!(moved > 0.1, "{}", moved);
+    for v in velocity.iter_mut() {
+        *v = -*v;
+    }
+    pendulum.advance(
+        &mut RK4::new(),
+        None,
+        t,
+        2.0 * t,
+        1e-4,
+        &mut position,
+        &mut velocity,
+    );
+    for (p, s) in position.iter().zip(start.iter()) {
+        assert_relative_eq!(*p, *s, epsilon = 1e-8);
+    }
+    for (v, s) in velocity.iter().zip(start_velocity.iter()) {
+        assert_relative_eq!(-*v, *s, epsilon = 1e-8);
+    }
+}
@@ -0,0 +1,415 @@
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use cgmath::{InnerSpace, MetricSpace, Vector3, Zero};
+use pendulum::Pendulum;
+use serde::Deserialize;
+
+use crate::{
+    image::Image,
+    scenario::{load_file, Field, Initial, Scenario, Simulation},
+};
+
+/// Sweep of two initial angles of a scenario, read from a TOML or JSON file
+///
+/// `scenario.initial` must be given by angles, the swept ones are overwritten.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MapConfig {
+    pub scenario: Scenario,
+    pub map: Map,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Map {
+    /// Horizontal axis, from left to right
+    pub x: Axis,
+    /// Vertical axis, from bottom to top
+    pub y: Axis,
+    pub width: usize,
+    pub height: usize,
+    #[serde(default)]
+    pub outcome: Outcome,
+    /// Number of coarser passes, each with twice the cell size of the next
+    #[serde(default)]
+    pub levels: u32,
+    /// How often flips are checked, `16 * scenario.dt` if not given
+    pub check_interval: Option<f64>,
+    /// Number of worker threads, all available cores if not given
+    pub threads: Option<usize>,
+    /// `.png` or `.ppm`, rewritten after each pass
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Axis {
+    /// Index of the swept link angle
+    pub angle: usize,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    /// Time until any link passes the upward direction, black if never
+    #[default]
+    Flip,
+    /// Point attractor nearest to the last bob at the end
+    Magnet,
+    /// Total energy at the end
+    Energy,
+}
+
+impl MapConfig {
+    pub fn load(path: &Path) -> Result<MapConfig, String> {
+        let config: MapConfig = load_file(path)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        self.scenario.validate()?;
+        if !matches!(self.scenario.initial, Initial::Angles { .. }) {
+            return Err(String::from("scenario.initial must be given by angles"));
+        }
+        for axis in [self.map.x, self.map.y] {
+            if axis.angle >= self.scenario.links.len() {
+                return Err(format!("no link angle {}", axis.angle));
+            }
+        }
+        if self.map.width == 0 || self.map.height == 0 {
+            return Err(String::from("map size must be positive"));
+        }
+        if matches!(self.map.check_interval, Some(interval) if interval <= 0.0) {
+            return Err(String::from("map.check_interval must be positive"));
+        }
+        if self.map.outcome == Outcome::Magnet && self.magnets().is_empty() {
+            return Err(String::from("magnet outcome needs point_attractor fields"));
+        }
+        Ok(())
+    }
+
+    fn magnets(&self) -> Vec<Vector3<f64>> {
+        self.scenario
+            .fields
+            .iter()
+            .filter_map(|f| match f {
+                Field::PointAttractor(p) => Some(p.center),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Initial angles of the cell at pixel (`px`, `py`)
+    fn angles(&self, px: usize, py: usize) -> Vec<f64> {
+        let mut angles = match &self.scenario.initial {
+            Initial::Angles { angles, .. } => angles.clone(),
+            Initial::Position { .. } => unreachable!(),
+        };
+        let (x, y) = (self.map.x, self.map.y);
+        let u = (px as f64 + 0.5) / self.map.width as f64;
+        let v = (py as f64 + 0.5) / self.map.height as f64;
+        angles[x.angle] = x.min + (x.max - x.min) * u;
+        angles[y.angle] = y.max - (y.max - y.min) * v;
+        angles
+    }
+
+    /// Run the pendulum from `angles` and measure the outcome, NaN if there is none
+    pub fn evaluate(&self, pendulum: &mut Pendulum, angles: &[f64]) -> Result<f64, String> {
+        let scenario = &self.scenario;
+        let axis = match &scenario.initial {
+            Initial::Angles { axis, .. } => *axis,
+            Initial::Position { .. } => unreachable!(),
+        };
+        let up = scenario.g.normalize();
+        let side = (axis - up * axis.dot(up)).normalize();
+        let link_angles = |root: Vector3<f64>, position: &[Vector3<f64>]| {
+            let mut last = root;
+            position
+                .iter()
+                .map(|&p| {
+                    let d = p - last;
+                    last = p;
+                    d.dot(side).atan2(-d.dot(up))
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let mut position = pendulum.pose_from_angles(pendulum.root_position(0.0), axis, angles)?;
+        let mut velocity = vec![Vector3::zero(); position.len()];
+        let mut unwrapped = angles.to_vec();
+        let mut last = link_angles(pendulum.root_position(0.0), &position);
+        let check = match self.map.outcome {
+            Outcome::Flip => self.map.check_interval.unwrap_or(scenario.dt * 16.0),
+            _ => scenario.duration,
+        };
+
+        let integrator = scenario.integrator()?;
+        let mut t = 0.0;
+        while t < scenario.duration {
+            let until = (t + check).min(scenario.duration);
+            t = pendulum.advance_with(
+                integrator,
+                None,
+                t,
+                until,
+                scenario.dt,
+                &mut position,
+                &mut velocity,
+            );
+            if self.map.outcome == Outcome::Flip {
+                let now = link_angles(pendulum.root_position(t), &position);
+                for ((theta, a), b) in unwrapped.iter_mut().zip(last.iter()).zip(now.iter()) {
+                    let mut d = b - a;
+                    if d > std::f64::consts::PI {
+                        d -= std::f64::consts::TAU;
+                    } else if d < -std::f64::consts::PI {
+                        d += std::f64::consts::TAU;
+                    }
+                    *theta += d;
+                    if theta.abs() > std::f64::consts::PI {
+                        return Ok(t);
+                    }
+                }
+                last = now;
+            }
+        }
+
+        Ok(match self.map.outcome {
+            Outcome::Flip => f64::NAN,
+            Outcome::Magnet => {
+                let end = position[position.len() - 1];
+                self.magnets()
+                    .iter()
+                    .enumerate()
+                    .min_by(|(_, a), (_, b)| a.distance2(end).total_cmp(&b.distance2(end)))
+                    .map(|(i, _)| i as f64)
+                    .unwrap_or(f64::NAN)
+            }
+            Outcome::Energy => {
+                pendulum.potential_energy(&position) + pendulum.kinetic_energy(&velocity)
+            }
+        })
+    }
+
+    /// Evaluate `cells` in parallel, each worker has its own `Pendulum`
+    fn evaluate_all(&self, cells: &[(usize, usize)]) -> Result<Vec<f64>, String> {
+        let threads = self.map.threads.unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        });
+        let next = &AtomicUsize::new(0);
+        let results = std::thread::scope(|s| {
+            let workers = (0..threads.max(1))
+                .map(|_| {
+                    s.spawn(move || -> Result<Vec<(usize, f64)>, String> {
+                        let Simulation { mut pendulum, .. } = self.scenario.build()?;
+                        let mut values = Vec::new();
+                        loop {
+                            let i = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(&(px, py)) = cells.get(i) else {
+                                break;
+                            };
+                            values.push((i, self.evaluate(&mut pendulum, &self.angles(px, py))?));
+                        }
+                        Ok(values)
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .map(|w| w.join().expect("worker panicked"))
+                .collect::<Vec<_>>()
+        });
+        let mut values = vec![f64::NAN; cells.len()];
+        for result in results {
+            for (i, v) in result? {
+                values[i] = v;
+            }
+        }
+        Ok(values)
+    }
+
+    /// Compute the map coarse to fine, writing the image after each pass
+    pub fn run(&self) -> Result<(), String> {
+        let (width, height) = (self.map.width, self.map.height);
+        let mut values = vec![f64::NAN; width * height];
+        for level in (0..=self.map.levels).rev() {
+            let wall = Instant::now();
+            let cells = refinement_cells(width, height, level, self.map.levels);
+            for (&(px, py), v) in cells.iter().zip(self.evaluate_all(&cells)?) {
+                values[py * width + px] = v;
+            }
+            self.write_image(&values, 1 << level)?;
+            eprintln!(
+                "level {}: {} cells in {:.2}s",
+                level,
+                cells.len(),
+                wall.elapsed().as_secs_f64()
+            );
+        }
+        Ok(())
+    }
+
+    /// Write `values` computed on the cells of size `step`
+    fn write_image(&self, values: &[f64], step: usize) -> Result<(), String> {
+        let (width, height) = (self.map.width, self.map.height);
+        let value = |px: usize, py: usize| values[(py - py % step) * width + px - px % step];
+        let (min, max) = values
+            .iter()
+            .filter(|v| v.is_finite())
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &v| {
+                (min.min(v), max.max(v))
+            });
+        let mut image = Image::new(width, height);
+        for py in 0..height {
+            for px in 0..width {
+                let v = value(px, py);
+                if v.is_nan() {
+                    continue;
+                }
+                let color = match self.map.outcome {
+                    Outcome::Flip => gradient((1.0 + v).ln() / (1.0 + self.scenario.duration).ln()),
+                    Outcome::Magnet => palette(v as usize),
+                    Outcome::Energy if max > min => gradient((v - min) / (max - min)),
+                    Outcome::Energy => gradient(0.5),
+                };
+                image.set(px, py, color);
+            }
+        }
+
+        let path = &self.map.output;
+        let w = BufWriter::new(File::create(path).map_err(|err| err.to_string())?);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("png") => image.write_png(w),
+            Some("ppm") => image.write_ppm(w),
+            _ => return Err(format!("{}: expect .png or .ppm", path.display())),
+        }
+        .map_err(|err| format!("{}: {}", path.display(), err))
+    }
+}
+
+/// Cells newly computed at `level`, those on the grid of `2^level` not on any coarser one
+fn refinement_cells(width: usize, height: usize, level: u32, levels: u32) -> Vec<(usize, usize)> {
+    let step = 1 << level;
+    let mut cells = Vec::new();
+    for py in (0..height).step_by(step) {
+        for px in (0..width).step_by(step) {
+            if level < levels && px % (step * 2) == 0 && py % (step * 2) == 0 {
+                continue;
+            }
+            cells.push((px, py));
+        }
+    }
+    cells
+}
+
+/// Dark purple to yellow for `v` in [0, 1]
+fn gradient(v: f64) -> [u8; 3] {
+    const STOPS: [[f64; 3]; 5] = [
+        [0.05, 0.03, 0.20],
+        [0.40, 0.10, 0.50],
+        [0.80, 0.25, 0.35],
+        [0.98, 0.55, 0.10],
+        [0.98, 0.95, 0.60],
+    ];
+    let v = v.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let i = (v as usize).min(STOPS.len() - 2);
+    let f = v - i as f64;
+    let mut color = [0; 3];
+    for (k, c) in color.iter_mut().enumerate() {
+        *c = ((STOPS[i][k] * (1.0 - f) + STOPS[i + 1][k] * f) * 255.0).round() as u8;
+    }
+    color
+}
+
+fn palette(i: usize) -> [u8; 3] {
+    const COLORS: [[u8; 3]; 6] = [
+        [230, 60, 60],
+        [60, 160, 230],
+        [240, 200, 40],
+        [80, 200, 100],
+        [180, 90, 220],
+        [240, 140, 40],
+    ];
+    COLORS[i % COLORS.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map_config(outcome: &str) -> MapConfig {
+        toml::from_str(&format!(
+            r#"
+[scenario]
+duration = 3.0
+dt = 1e-3
+links = [{{ length = 0.3, mass = 1.0 }}, {{ length = 0.3, mass = 1.0 }}]
+initial = {{ angles = [0.0, 0.0] }}
+
+[[scenario.fields]]
+type = "point_attractor"
+center = [0.1, -0.7, 0.0]
+strength = 0.1
+softening = 0.05
+
+[[scenario.fields]]
+type = "point_attractor"
+center = [-0.1, -0.7, 0.0]
+strength = 0.1
+softening = 0.05
+
+[map]
+x = {{ angle = 0, min = -3.0, max = 3.0 }}
+y = {{ angle = 1, min = -3.0, max = 3.0 }}
+width = 8
+height = 8
+outcome = "{}"
+output = "map.png"
+"#,
+            outcome
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_refinement_covers_grid() {
+        let (width, height, levels) = (13, 7, 3);
+        let mut count = vec![0; width * height];
+        for level in (0..=levels).rev() {
+            for (px, py) in refinement_cells(width, height, level, levels) {
+                count[py * width + px] += 1;
+            }
+        }
+        assert!(count.iter().all(|&c| c == 1));
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let config = map_config("flip");
+        config.validate().unwrap();
+        assert_eq!(config.angles(0, 0), vec![-2.625, 2.625]);
+        let Simulation { mut pendulum, .. } = config.scenario.build().unwrap();
+        let t = config.evaluate(&mut pendulum, &[3.0, 3.0]).unwrap();
+        assert!(t > 0.0 && t < 3.0, "{}", t);
+        assert!(config
+            .evaluate(&mut pendulum, &[0.1, 0.1])
+            .unwrap()
+            .is_nan());
+
+        let config = map_config("magnet");
+        let Simulation { mut pendulum, .. } = config.scenario.build().unwrap();
+        let magnet = config.evaluate(&mut pendulum, &[0.5, 0.0]).unwrap();
+        assert!([0.0, 1.0].contains(&magnet), "{}", magnet);
+    }
+}
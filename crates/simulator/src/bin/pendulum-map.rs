@@ -0,0 +1,15 @@
+use std::path::Path;
+
+use simulator::basin::MapConfig;
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.len() != 2 {
+        eprintln!("usage: {} <map.toml|map.json>", args[0]);
+        std::process::exit(2);
+    }
+    if let Err(err) = MapConfig::load(Path::new(&args[1])).and_then(|c| c.run()) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
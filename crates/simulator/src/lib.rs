@@ -0,0 +1,3 @@
+pub mod basin;
+pub mod image;
+pub mod scenario;
@@ -0,0 +1,99 @@
+use std::{fs::File, io::BufWriter, path::Path, time::Instant};
+
+use cgmath::MetricSpace;
+use itertools::Itertools;
+use pendulum::{LinkKind, Recorder};
+use serde::Serialize;
+use simulator::scenario::{Scenario, Simulation};
+
+#[derive(Debug, Serialize)]
+struct Diagnostics {
+    time: f64,
+    samples: usize,
+    initial_energy: f64,
+    final_energy: f64,
+    /// max |E(t) - E(0)|
+    max_energy_error: f64,
+    /// max ||x_i - x_{i-1}| - l_i|, only stretching counts for strings
+    max_length_error: f64,
+    wall_seconds: f64,
+}
+
+fn run(scenario: &Scenario) -> Result<(), String> {
+    let Simulation {
+        mut pendulum,
+        mut position,
+        mut velocity,
+    } = scenario.build()?;
+    let mut recorder = Recorder::new(&pendulum, scenario.interval())?;
+
+    let wall = Instant::now();
+    let time = pendulum.advance_with(
+        scenario.integrator()?,
+        Some(&mut recorder),
+        0.0,
+        scenario.duration,
+        scenario.dt,
+        &mut position,
+        &mut velocity,
+    );
+    let wall_seconds = wall.elapsed().as_secs_f64();
+
+    let samples = recorder.samples();
+    let energy = |i: usize| samples[i].kinetic_energy + samples[i].potential_energy;
+    let mut max_energy_error = 0.0f64;
+    let mut max_length_error = 0.0f64;
+    for (i, sample) in samples.iter().enumerate() {
+        max_energy_error = max_energy_error.max((energy(i) - energy(0)).abs());
+        let chain = std::iter::once(&sample.root_position).chain(sample.position.iter());
+        for ((a, b), link) in chain.tuple_windows().zip(scenario.links.iter()) {
+            let error = a.distance(*b) - link.length;
+            let error = match link.kind {
+                LinkKind::Rod => error.abs(),
+                LinkKind::String { .. } => error.max(0.0),
+            };
+            max_length_error = max_length_error.max(error);
+        }
+    }
+    let diagnostics = Diagnostics {
+        time,
+        samples: samples.len(),
+        initial_energy: energy(0),
+        final_energy: energy(samples.len() - 1),
+        max_energy_error,
+        max_length_error,
+        wall_seconds,
+    };
+
+    if let Some(path) = scenario.output.trajectory.as_ref() {
+        let w = BufWriter::new(File::create(path).map_err(|err| err.to_string())?);
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("bin") => recorder.write_binary(w),
+            _ => recorder.write_csv(w),
+        }
+        .map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    match scenario.output.diagnostics.as_ref() {
+        Some(path) => {
+            let w = BufWriter::new(File::create(path).map_err(|err| err.to_string())?);
+            serde_json::to_writer_pretty(w, &diagnostics).map_err(|err| err.to_string())?;
+        }
+        None => println!(
+            "{}",
+            serde_json::to_string_pretty(&diagnostics).map_err(|err| err.to_string())?
+        ),
+    }
+    Ok(())
+}
+
+fn main() {
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.len() != 2 {
+        eprintln!("usage: {} <scenario.toml|scenario.json>", args[0]);
+        std::process::exit(2);
+    }
+    if let Err(err) = Scenario::load(Path::new(&args[1])).and_then(|s| run(&s)) {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+}
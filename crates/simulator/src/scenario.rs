@@ -0,0 +1,338 @@
+use std::path::{Path, PathBuf};
+
+use cgmath::{vec3, Vector3, Zero};
+use pendulum::{
+    CentralGravity, CubicSpline, FixedPoint, Harmonic, HarmonicWell, Integrator, LinearDrag,
+    LinkKind, Oscillate1d, Pendulum, PointAttractor, Sinusoid, UniformElectric,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+
+/// Everything needed to run a simulation, read from a TOML or JSON file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    #[serde(default = "default_g")]
+    pub g: Vector3<f64>,
+    pub links: Vec<Link>,
+    pub initial: Initial,
+    #[serde(default)]
+    pub root: Root,
+    #[serde(default)]
+    pub fields: Vec<Field>,
+    #[serde(default = "default_integrator")]
+    pub integrator: String,
+    pub duration: f64,
+    pub dt: f64,
+    #[serde(default)]
+    pub output: Output,
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Link {
+    pub length: f64,
+    pub mass: f64,
+    #[serde(default)]
+    pub kind: LinkKind,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Initial {
+    /// Angles from the downward direction in the plane of gravity and `axis`
+    Angles {
+        angles: Vec<f64>,
+        #[serde(default = "default_axis")]
+        axis: Vector3<f64>,
+    },
+    Position {
+        position: Vec<Vector3<f64>>,
+        velocity: Option<Vec<Vector3<f64>>>,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Root {
+    Fixed {
+        position: Vector3<f64>,
+    },
+    Oscillate {
+        a: Vector3<f64>,
+        b: Vector3<f64>,
+        omega: f64,
+        #[serde(default)]
+        theta0: f64,
+    },
+    /// Waypoints in a `t,x,y,z` CSV file
+    Spline {
+        path: PathBuf,
+    },
+    /// Sum of sinusoids, see `pendulum::Sinusoid`
+    Harmonic {
+        terms: Vec<Sinusoid>,
+    },
+}
+
+/// External force fields, see `pendulum::ForceField`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Field {
+    PointAttractor(PointAttractor),
+    CentralGravity(CentralGravity),
+    UniformElectric(UniformElectric),
+    HarmonicWell(HarmonicWell),
+    LinearDrag(LinearDrag),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Output {
+    /// `.csv` or `.bin` trajectory, nothing is written if not given
+    pub trajectory: Option<PathBuf>,
+    /// Sampling interval of the trajectory and diagnostics, `duration / 1000` if not given
+    pub interval: Option<f64>,
+    /// JSON diagnostics, printed to stdout if not given
+    pub diagnostics: Option<PathBuf>,
+}
+
+fn default_g() -> Vector3<f64> {
+    vec3(0.0, 9.8, 0.0)
+}
+
+fn default_axis() -> Vector3<f64> {
+    vec3(1.0, 0.0, 0.0)
+}
+
+fn default_integrator() -> String {
+    String::from("rk4")
+}
+
+/// Pendulum ready to run and its initial state
+#[derive(Debug)]
+pub struct Simulation {
+    pub pendulum: Pendulum,
+    pub position: Vec<Vector3<f64>>,
+    pub velocity: Vec<Vector3<f64>>,
+}
+
+impl Default for Root {
+    fn default() -> Self {
+        Root::Fixed {
+            position: Vector3::zero(),
+        }
+    }
+}
+
+/// Read a TOML or JSON file according to its extension
+pub fn load_file<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let data =
+        std::fs::read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("json") => serde_json::from_str(&data).map_err(|err| err.to_string()),
+        Some("toml") => toml::from_str(&data).map_err(|err| err.to_string()),
+        _ => Err(format!("{}: expect .toml or .json", path.display())),
+    }
+}
+
+impl Scenario {
+    pub fn load(path: &Path) -> Result<Scenario, String> {
+        let scenario: Scenario = load_file(path)?;
+        scenario.validate()?;
+        Ok(scenario)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.duration <= 0.0 {
+            return Err(String::from("duration must be positive"));
+        }
+        if self.dt <= 0.0 {
+            return Err(String::from("dt must be positive"));
+        }
+        if matches!(self.output.interval, Some(interval) if interval <= 0.0) {
+            return Err(String::from("output.interval must be positive"));
+        }
+        self.integrator()?;
+        Ok(())
+    }
+
+    pub fn integrator(&self) -> Result<Integrator, String> {
+        Integrator::from_name(&self.integrator)
+    }
+
+    pub fn interval(&self) -> f64 {
+        self.output.interval.unwrap_or(self.duration / 1000.0)
+    }
+
+    pub fn length_mass(&self) -> Vec<(f64, f64)> {
+        self.links.iter().map(|l| (l.length, l.mass)).collect()
+    }
+
+    /// Build the pendulum with its root driver and the initial state
+    pub fn build(&self) -> Result<Simulation, String> {
+        let mut pendulum = Pendulum::new(self.g, &self.length_mass())?;
+        for (i, link) in self.links.iter().enumerate() {
+            pendulum.set_link_kind(i, link.kind)?;
+        }
+        match self.root.clone() {
+            Root::Fixed { position } => pendulum.set_root(FixedPoint(position)),
+            Root::Oscillate {
+                a,
+                b,
+                omega,
+                theta0,
+            } => pendulum.set_root(Oscillate1d::new(a, b, omega, theta0)),
+            Root::Spline { path } => {
+                let data = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+                let spline = CubicSpline::from_csv(&data)
+                    .map_err(|err| format!("{}: {}", path.display(), err))?;
+                pendulum.set_root(spline)
+            }
+            Root::Harmonic { terms } => pendulum.set_root(Harmonic::new(terms)),
+        }
+        for field in self.fields.iter().cloned() {
+            match field {
+                Field::PointAttractor(f) => pendulum.add_field(f),
+                Field::CentralGravity(f) => pendulum.add_field(f),
+                Field::UniformElectric(f) => pendulum.add_field(f),
+                Field::HarmonicWell(f) => pendulum.add_field(f),
+                Field::LinearDrag(f) => pendulum.add_field(f),
+            }
+        }
+        let n = self.links.len();
+        let root = pendulum.root_position(0.0);
+        let (position, velocity) = match &self.initial {
+            Initial::Angles { angles, axis } => (
+                pendulum.pose_from_angles(root, *axis, angles)?,
+                vec![Vector3::zero(); n],
+            ),
+            Initial::Position { position, velocity } => (
+                position.clone(),
+                velocity.clone().unwrap_or_else(|| vec![Vector3::zero(); n]),
+            ),
+        };
+        if position.len() != n || velocity.len() != n {
+            return Err(format!(
+                "expect {} bobs, but initial position has {} and velocity has {}",
+                n,
+                position.len(),
+                velocity.len()
+            ));
+        }
+        Ok(Simulation {
+            pendulum,
+            position,
+            velocity,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_toml() {
+        let scenario: Scenario = toml::from_str(
+            r#"
+duration = 10.0
+dt = 1e-3
+
+[[links]]
+length = 0.3
+mass = 1.0
+
+[[links]]
+length = 0.2
+mass = 0.5
+kind = { type = "string", restitution = 0.5 }
+
+[initial]
+angles = [1.0, 0.5]
+
+[root]
+type = "oscillate"
+a = [0.1, 0.0, 0.0]
+b = [0.0, 0.0, 0.0]
+omega = 3.0
+
+[[fields]]
+type = "point_attractor"
+center = [0.0, -0.6, 0.0]
+strength = 0.1
+softening = 0.05
+
+[[fields]]
+type = "linear_drag"
+coefficient = 0.2
+
+[output]
+trajectory = "out.csv"
+"#,
+        )
+        .unwrap();
+        assert_eq!(scenario.g, vec3(0.0, 9.8, 0.0));
+        assert_eq!(scenario.integrator(), Ok(Integrator::Rk4));
+        assert_eq!(scenario.interval(), 1e-2);
+        assert!(matches!(
+            scenario.fields.as_slice(),
+            [Field::PointAttractor(_), Field::LinearDrag(_)]
+        ));
+        let sim = scenario.build().unwrap();
+        assert_eq!(sim.pendulum.length_mass(), &[(0.3, 1.0), (0.2, 0.5)]);
+        assert_eq!(sim.pendulum.link_kind(0), LinkKind::Rod);
+        assert_eq!(
+            sim.pendulum.link_kind(1),
+            LinkKind::String { restitution: 0.5 }
+        );
+        assert_eq!(sim.position.len(), 2);
+        assert_eq!(sim.velocity, vec![Vector3::zero(); 2]);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let scenario: Scenario = serde_json::from_str(
+            r#"{
+  "links": [{"length": 1.0, "mass": 1.0}, {"length": 1.0, "mass": 1.0}],
+  "initial": {"position": [[1.0, 0.0, 0.0], [2.0, 0.0, 0.0]]},
+  "duration": 1.0,
+  "dt": 0.01
+}"#,
+        )
+        .unwrap();
+        assert!(matches!(scenario.root, Root::Fixed { .. }));
+        assert!(scenario.fields.is_empty());
+        let sim = scenario.build().unwrap();
+        assert_eq!(sim.position[1], vec3(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn test_parse_harmonic_root() {
+        let root: Root = toml::from_str(
+            r#"
+type = "harmonic"
+
+[[terms]]
+amplitude = [0.1, 0.0, 0.0]
+omega = 2.0
+sweep = 0.5
+
+[[terms]]
+amplitude = [0.0, 0.0, 0.1]
+phase = [0.0, 0.0, 1.5]
+omega = 2.0
+"#,
+        )
+        .unwrap();
+        match root {
+            Root::Harmonic { terms } => {
+                assert_eq!(terms.len(), 2);
+                assert_eq!(terms[0].phase, Vector3::zero());
+                assert_eq!(terms[0].sweep, 0.5);
+                assert_eq!(terms[1].sweep, 0.0);
+            }
+            _ => panic!("expect harmonic root"),
+        }
+    }
+}
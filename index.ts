@@ -1,4 +1,4 @@
-import {App, Mouse} from "multi-pendulum";
+import {App, Pointers} from "multi-pendulum";
 
 const canvas = document.getElementById("canvas") as HTMLCanvasElement | null;
 if (!canvas) {
@@ -6,17 +6,166 @@ if (!canvas) {
 }
 
 const app = new App(canvas);
-const e0 = app.potential_energy() + app.kinetic_energy();
-const mouseState = new Mouse;
+// reference of ΔE, taken again whenever the pendulum is rebuilt
+let e0 = app.potential_energy() + app.kinetic_energy();
+const pointers = new Pointers;
 
-canvas.addEventListener('mousedown', (event) => {
-  mouseState.add_mousedown(event.offsetX, event.offsetY, event.button);
+// touches go to the app instead of scrolling or zooming the page
+canvas.style.touchAction = "none";
+canvas.addEventListener('pointerdown', (event) => {
+  canvas.setPointerCapture(event.pointerId);
+  pointers.add_pointerdown(event.pointerId, event.offsetX, event.offsetY, event.button, event.pointerType);
 })
-canvas.addEventListener('mouseup', (event) => {
-  mouseState.add_mouseup(event.offsetX, event.offsetY, event.button);
+canvas.addEventListener('pointerup', (event) => {
+  pointers.add_pointerup(event.pointerId, event.offsetX, event.offsetY);
 })
-canvas.addEventListener('mousemove', (event) => {
-  mouseState.add_mousemove(event.offsetX, event.offsetY);
+canvas.addEventListener('pointermove', (event) => {
+  pointers.add_pointermove(event.pointerId, event.offsetX, event.offsetY);
+})
+canvas.addEventListener('pointercancel', (event) => {
+  pointers.add_pointercancel(event.pointerId);
+})
+canvas.addEventListener('wheel', (event) => {
+  event.preventDefault();
+  pointers.add_wheel(event.deltaMode === WheelEvent.DOM_DELTA_PIXEL ? event.deltaY : event.deltaY * 40);
+}, {passive: false})
+// the right button flicks bobs
+canvas.addEventListener('contextmenu', (event) => {
+  event.preventDefault();
+})
+
+document.getElementById("reset-camera")?.addEventListener('click', () => {
+  app.reset_camera();
+})
+document.getElementById("focus-selected")?.addEventListener('click', () => {
+  app.focus_selected();
+})
+const followSelect = document.getElementById("follow") as HTMLSelectElement;
+for (const name of App.camera_follow_modes()) {
+  followSelect.add(new Option(name, name));
+}
+followSelect.addEventListener('change', () => {
+  app.set_camera_follow(followSelect.value);
+})
+const fovInput = document.getElementById("fov") as HTMLInputElement;
+fovInput.addEventListener('change', () => {
+  app.set_fov(Number(fovInput.value));
+})
+
+const pauseButton = document.getElementById("pause") as HTMLButtonElement;
+pauseButton.addEventListener('click', () => {
+  if (app.is_paused()) {
+    app.resume();
+    pauseButton.innerText = "Pause";
+  } else {
+    app.pause();
+    pauseButton.innerText = "Resume";
+  }
+})
+document.getElementById("single-step")?.addEventListener('click', () => {
+  app.single_step();
+})
+const timeScaleInput = document.getElementById("time-scale") as HTMLInputElement;
+timeScaleInput.addEventListener('change', () => {
+  app.set_time_scale(Number(timeScaleInput.value));
+  timeScaleInput.value = app.time_scale().toString();
+})
+
+function parseNumbers(id: string): Float64Array {
+  const input = document.getElementById(id) as HTMLInputElement;
+  return new Float64Array(input.value.split(",").map(Number));
+}
+document.getElementById("set-chain")?.addEventListener('click', () => {
+  app.set_chain(parseNumbers("lengths"), parseNumbers("masses"));
+  e0 = app.potential_energy() + app.kinetic_energy();
+})
+document.getElementById("set-gravity")?.addEventListener('click', () => {
+  const [x, y, z] = parseNumbers("gravity");
+  app.set_gravity(x, y, z);
+  e0 = app.potential_energy() + app.kinetic_energy();
+})
+const poseSelect = document.getElementById("pose") as HTMLSelectElement;
+for (const name of App.pose_presets()) {
+  poseSelect.add(new Option(name, name));
+}
+document.getElementById("reset-pose")?.addEventListener('click', () => {
+  app.reset_pose(poseSelect.value);
+  e0 = app.potential_energy() + app.kinetic_energy();
+})
+
+const integratorSelect = document.getElementById("integrator") as HTMLSelectElement;
+for (const name of App.integrator_names()) {
+  integratorSelect.add(new Option(name, name));
+}
+integratorSelect.value = app.integrator();
+integratorSelect.addEventListener('change', () => {
+  app.set_integrator(integratorSelect.value);
+})
+const substepsInput = document.getElementById("substeps") as HTMLInputElement;
+substepsInput.value = app.substeps().toString();
+substepsInput.addEventListener('change', () => {
+  app.set_substeps(Number(substepsInput.value));
+  substepsInput.value = app.substeps().toString();
+})
+const trailLengthInput = document.getElementById("trail-length") as HTMLInputElement;
+trailLengthInput.value = app.trail_length().toString();
+trailLengthInput.addEventListener('change', () => {
+  app.set_trail_length(Math.max(0, Number(trailLengthInput.value)));
+  trailLengthInput.value = app.trail_length().toString();
+})
+const shadowSelect = document.getElementById("shadow-mode") as HTMLSelectElement;
+for (const name of App.shadow_modes()) {
+  shadowSelect.add(new Option(name, name));
+}
+shadowSelect.value = app.shadow_mode();
+shadowSelect.addEventListener('change', () => {
+  app.set_shadow_mode(shadowSelect.value);
+})
+
+function download(data: BlobPart, type: string, filename: string): void {
+  const url = URL.createObjectURL(new Blob([data], {type}));
+  const a = document.createElement("a");
+  a.href = url;
+  a.download = filename;
+  a.click();
+  URL.revokeObjectURL(url);
+}
+
+const recordButton = document.getElementById("record") as HTMLButtonElement;
+recordButton.addEventListener('click', () => {
+  if (app.is_recording()) {
+    app.stop_recording();
+    recordButton.innerText = "Start recording";
+  } else {
+    app.start_recording(1 / 60);
+    recordButton.innerText = "Stop recording";
+  }
+})
+document.getElementById("download-csv")?.addEventListener('click', () => {
+  download(app.recording_csv(), "text/csv", "trajectory.csv");
+})
+document.getElementById("download-binary")?.addEventListener('click', () => {
+  download(app.recording_binary(), "application/octet-stream", "trajectory.bin");
+})
+
+const recordInputButton = document.getElementById("record-input") as HTMLButtonElement;
+let inputRecording = false;
+recordInputButton.addEventListener('click', () => {
+  if (inputRecording) {
+    download(app.stop_input_recording(), "application/json", "input-log.json");
+    recordInputButton.innerText = "Start input recording";
+  } else {
+    app.start_input_recording();
+    recordInputButton.innerText = "Stop input recording";
+  }
+  inputRecording = !inputRecording;
+})
+const replayInput = document.getElementById("replay-input") as HTMLInputElement;
+replayInput.addEventListener('change', async () => {
+  const file = replayInput.files?.item(0);
+  if (file) {
+    app.start_replay(await file.text());
+  }
 })
 
 function step(timestamp: DOMHighResTimeStamp): void {
@@ -24,7 +173,9 @@ function step(timestamp: DOMHighResTimeStamp): void {
   if (!energyDiv) {
     throw new Error("No 'energy'");
   }
-  app.tick(timestamp, mouseState);
+  app.tick(timestamp, pointers);
+  // panning and focusing stop following
+  followSelect.value = app.camera_follow();
   const pe = app.potential_energy();
   const ke = app.kinetic_energy();
   const unit = app.unit_energy();
@@ -1,10 +1,69 @@
 #version 300 es
 precision highp float;
+precision highp sampler2DShadow;
+
+// same as MAX_BLOBS in renderer.rs
+#define MAX_BLOBS 32
 
 in vec4 vert_color;
+in float vert_diffuse;
+in vec3 world_position;
+in vec4 shadow_coord;
 
 out vec4 frag_color;
 
+uniform vec3 light_direction;
+// 0: off, 1: blob, 2: map
+uniform int shadow_mode;
+uniform sampler2DShadow shadow_map;
+// center and radius of the spheres casting blob shadows
+uniform vec4 blobs[MAX_BLOBS];
+uniform int blob_count;
+
+// 3x3 samples of the depth from the light, each filtered by the hardware
+float map_light() {
+    vec3 p = shadow_coord.xyz / shadow_coord.w;
+    if (any(lessThan(p, vec3(0.0))) || any(greaterThan(p, vec3(1.0)))) {
+        return 1.0;
+    }
+    vec2 texel = 1.0 / vec2(textureSize(shadow_map, 0));
+    float light = 0.0;
+    for (int x = -1; x <= 1; x++) {
+        for (int y = -1; y <= 1; y++) {
+            light += texture(shadow_map, vec3(p.xy + vec2(x, y) * texel, p.z));
+        }
+    }
+    return light / 9.0;
+}
+
+// soft discs behind the spheres, blurring with the distance
+float blob_light() {
+    vec3 l = normalize(light_direction);
+    float light = 1.0;
+    for (int i = 0; i < MAX_BLOBS; i++) {
+        if (i >= blob_count) {
+            break;
+        }
+        vec3 v = blobs[i].xyz - world_position;
+        float r = blobs[i].w;
+        float along = dot(v, l);
+        if (along > r) {
+            float off = length(v - l * along);
+            light = min(light, smoothstep(0.5 * r, r + 0.05 * along, off));
+        }
+    }
+    return light;
+}
+
 void main() {
-    frag_color = vert_color;
+    vec4 ambient_color = vec4(0.1, 0.1, 0.1, 1.0);
+    float light = 1.0;
+    if (shadow_mode == 1) {
+        light = blob_light();
+    } else if (shadow_mode == 2) {
+        light = map_light();
+    }
+    // in the shadow as dark as facing away from the light
+    float diffuse = mix(0.4, vert_diffuse, light);
+    frag_color = vert_color * vec4(vec3(diffuse), 1.0) + ambient_color;
 }
@@ -0,0 +1,14 @@
+#version 300 es
+precision highp float;
+
+in vec3 position;
+in vec4 color;
+
+out vec4 vert_color;
+
+uniform mat4 mvp_matrix;
+
+void main() {
+    vert_color = color;
+    gl_Position = mvp_matrix * vec4(position, 1.0);
+}
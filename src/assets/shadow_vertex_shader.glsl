@@ -0,0 +1,10 @@
+#version 300 es
+precision highp float;
+
+in vec3 position;
+
+uniform mat4 mvp_matrix;
+
+void main() {
+    gl_Position = mvp_matrix * vec4(position, 1.0);
+}
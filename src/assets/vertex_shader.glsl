@@ -6,15 +6,22 @@ in vec3 normal;
 in vec4 color;
 
 out vec4 vert_color;
+out float vert_diffuse;
+out vec3 world_position;
+out vec4 shadow_coord;
 
 uniform mat4 mvp_matrix;
 uniform mat4 inv_matrix;
+uniform mat4 model_matrix;
+uniform mat4 shadow_matrix;
 uniform vec3 light_direction;
 
 void main() {
-    vec4 ambient_color = vec4(0.1, 0.1, 0.1, 1.0);
     vec3  inv_light = normalize(inv_matrix * vec4(light_direction, 0.0)).xyz;
-    float diffuse  = clamp(dot(normal, inv_light), 0.4, 1.0);
-    vert_color = color * vec4(vec3(diffuse), 1.0) + ambient_color;
+    vert_diffuse = clamp(dot(normal, inv_light), 0.4, 1.0);
+    vert_color = color;
+    vec4 world = model_matrix * vec4(position, 1.0);
+    world_position = world.xyz;
+    shadow_coord = shadow_matrix * world;
     gl_Position = mvp_matrix * vec4(position, 1.0);
 }
@@ -0,0 +1,354 @@
+use cgmath::{
+    perspective, vec3, Deg, InnerSpace, Matrix4, One, Quaternion, Rad, Rotation, Rotation3,
+    Vector3, Zero,
+};
+use serde::{Deserialize, Serialize};
+
+/// Time constant of the damped zoom and focus [s]
+const SMOOTHING: f64 = 0.1;
+
+/// Time constant of the orbit keeping on after a release [s]
+const INERTIA: f64 = 0.3;
+
+/// Range of `Camera::distance` [m]
+const MIN_DISTANCE: f64 = 0.05;
+const MAX_DISTANCE: f64 = 50.0;
+
+/// Space around the chain framed by `Follow::Chain`
+const FRAME_MARGIN: f64 = 1.3;
+
+/// What the camera keeps looking at
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Follow {
+    /// Stays where the user put it
+    #[default]
+    Free,
+    Root,
+    CenterOfMass,
+    LastBob,
+    /// Looks at and backs off to keep the whole chain in view
+    Chain,
+}
+
+/// Orbit camera looking at `target` from `distance`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "CameraData")]
+pub struct Camera {
+    /// from the world to the camera
+    pub rotation: Quaternion<f64>,
+    pub target: Vector3<f64>,
+    pub distance: f64,
+    /// vertical field of view [deg]
+    pub fov: f64,
+    pub follow: Follow,
+    /// where `target` and `distance` move to
+    goal_target: Vector3<f64>,
+    goal_distance: f64,
+    /// angular velocity of the orbit in the camera frame [rad/s]
+    spin: Vector3<f64>,
+    orbited: bool,
+}
+
+/// `Camera` as read, where the motion in progress may be left out, e.g. in hand-written JSON
+#[derive(Deserialize)]
+struct CameraData {
+    rotation: Quaternion<f64>,
+    target: Vector3<f64>,
+    distance: f64,
+    fov: f64,
+    #[serde(default)]
+    follow: Follow,
+    goal_target: Option<Vector3<f64>>,
+    goal_distance: Option<f64>,
+    #[serde(default = "Vector3::zero")]
+    spin: Vector3<f64>,
+    #[serde(default)]
+    orbited: bool,
+}
+
+impl From<CameraData> for Camera {
+    /// At rest where the camera is if the motion is missing
+    fn from(data: CameraData) -> Camera {
+        Camera {
+            rotation: data.rotation,
+            target: data.target,
+            distance: data.distance,
+            fov: data.fov,
+            follow: data.follow,
+            goal_target: data.goal_target.unwrap_or(data.target),
+            goal_distance: data.goal_distance.unwrap_or(data.distance),
+            spin: data.spin,
+            orbited: data.orbited,
+        }
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera {
+            rotation: Quaternion::one(),
+            target: Vector3::zero(),
+            distance: 1.5,
+            fov: 90.0,
+            follow: Follow::Free,
+            goal_target: Vector3::zero(),
+            goal_distance: 1.5,
+            spin: Vector3::zero(),
+            orbited: false,
+        }
+    }
+}
+
+impl Follow {
+    pub const ALL: [Follow; 5] = [
+        Follow::Free,
+        Follow::Root,
+        Follow::CenterOfMass,
+        Follow::LastBob,
+        Follow::Chain,
+    ];
+
+    pub fn from_name(name: &str) -> Result<Follow, String> {
+        Follow::ALL
+            .into_iter()
+            .find(|f| f.name() == name)
+            .ok_or_else(|| format!("unknown camera follow mode '{}'", name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Follow::Free => "free",
+            Follow::Root => "root",
+            Follow::CenterOfMass => "center-of-mass",
+            Follow::LastBob => "last-bob",
+            Follow::Chain => "chain",
+        }
+    }
+}
+
+impl Camera {
+    pub fn view_matrix(&self) -> Matrix4<f64> {
+        Matrix4::from_translation(vec3(0.0, 0.0, -self.distance))
+            * Matrix4::from(self.rotation)
+            * Matrix4::from_translation(-self.target)
+    }
+
+    pub fn projection_matrix(&self, aspect: f64) -> Matrix4<f64> {
+        perspective(Deg(self.fov), aspect, self.distance * 1e-2, 1e4)
+    }
+
+    /// Unit vector from `target` to the eye
+    pub fn toward_eye(&self) -> Vector3<f64> {
+        self.rotation.invert().rotate_vector(vec3(0.0, 0.0, 1.0))
+    }
+
+    /// Turn around `target` by a drag of `(dx, dy)` in units of the canvas width over `dt` seconds
+    pub fn orbit(&mut self, (dx, dy): (f64, f64), dt: f64) {
+        let axis = vec3(dy, dx, 0.0);
+        let angle = 2.0 * std::f64::consts::PI * axis.magnitude();
+        self.spin = if angle > 0.0 && dt > 0.0 {
+            axis.normalize() * (angle / dt)
+        } else {
+            Vector3::zero()
+        };
+        self.orbited = true;
+        self.turn(axis, angle);
+    }
+
+    /// Turn around the line of sight, counterclockwise on the screen
+    pub fn roll(&mut self, angle: f64) {
+        self.turn(vec3(0.0, 0.0, 1.0), angle);
+    }
+
+    /// Move `target` along the screen by `(dx, dy)` in units of the canvas height, stops following
+    pub fn pan(&mut self, (dx, dy): (f64, f64)) {
+        self.follow = Follow::Free;
+        let inverse = self.rotation.invert();
+        let right = inverse.rotate_vector(vec3(1.0, 0.0, 0.0));
+        let up = inverse.rotate_vector(vec3(0.0, 1.0, 0.0));
+        // the canvas height spans 2 tan(fov / 2) distance at the target
+        let scale = 2.0 * self.distance * (Rad::from(Deg(self.fov)).0 / 2.0).tan();
+        let shift = (up * dy - right * dx) * scale;
+        self.target += shift;
+        self.goal_target += shift;
+    }
+
+    /// Get closer by `factor` > 1, away by `factor` < 1
+    pub fn zoom(&mut self, factor: f64) {
+        if factor > 0.0 {
+            self.goal_distance = (self.goal_distance / factor).clamp(MIN_DISTANCE, MAX_DISTANCE);
+        }
+    }
+
+    /// Look at `point` smoothly, stops following
+    pub fn focus(&mut self, point: Vector3<f64>) {
+        self.follow = Follow::Free;
+        self.goal_target = point;
+    }
+
+    /// Move toward the chain of `root` and the bobs at `position` with `mass` as `follow` says
+    ///
+    /// `aspect` is the width over the height of the canvas.
+    pub fn track(
+        &mut self,
+        root: Vector3<f64>,
+        position: &[Vector3<f64>],
+        mass: &[f64],
+        aspect: f64,
+    ) {
+        match self.follow {
+            Follow::Free => (),
+            Follow::Root => self.goal_target = root,
+            Follow::CenterOfMass => {
+                let total = mass.iter().sum::<f64>();
+                self.goal_target = position
+                    .iter()
+                    .zip(mass.iter())
+                    .fold(Vector3::zero(), |c, (&x, &m)| c + x * m)
+                    / total;
+            }
+            Follow::LastBob => self.goal_target = position.last().copied().unwrap_or(root),
+            Follow::Chain => {
+                let points = std::iter::once(&root).chain(position.iter());
+                let (min, max) = points.clone().fold((root, root), |(min, max), p| {
+                    (
+                        vec3(min.x.min(p.x), min.y.min(p.y), min.z.min(p.z)),
+                        vec3(max.x.max(p.x), max.y.max(p.y), max.z.max(p.z)),
+                    )
+                });
+                let center = (min + max) / 2.0;
+                let radius = points.map(|p| (p - center).magnitude()).fold(0.0, f64::max);
+                // the narrower of the vertical and horizontal fields of view
+                let half = (Rad::from(Deg(self.fov)).0 / 2.0).tan() * aspect.min(1.0);
+                self.goal_target = center;
+                self.goal_distance = (radius * FRAME_MARGIN * (1.0 + half * half).sqrt() / half)
+                    .clamp(MIN_DISTANCE, MAX_DISTANCE);
+            }
+        }
+    }
+
+    /// Back to the initial view, keeping the field of view and the follow mode
+    pub fn reset(&mut self) {
+        *self = Camera {
+            fov: self.fov,
+            follow: self.follow,
+            target: self.target,
+            distance: self.distance,
+            ..Camera::default()
+        };
+    }
+
+    /// Let the damped motion go on for `dt` seconds
+    pub fn update(&mut self, dt: f64) {
+        if !self.orbited {
+            let angle = self.spin.magnitude() * dt;
+            if angle > 0.0 {
+                self.turn(self.spin, angle);
+            }
+            self.spin *= (-dt / INERTIA).exp();
+        }
+        self.orbited = false;
+        let k = 1.0 - (-dt / SMOOTHING).exp();
+        self.target += (self.goal_target - self.target) * k;
+        self.distance *= (self.goal_distance / self.distance).powf(k);
+    }
+
+    /// Rotate the view around `axis` in the camera frame
+    fn turn(&mut self, axis: Vector3<f64>, angle: f64) {
+        if angle != 0.0 {
+            self.rotation = (Quaternion::from_axis_angle(axis.normalize(), Rad(angle))
+                * self.rotation)
+                .normalize();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::assert_relative_eq;
+
+    /// Settle the damped motion
+    fn settle(camera: &mut Camera) {
+        for _ in 0..100 {
+            camera.update(0.1);
+        }
+    }
+
+    #[test]
+    fn test_zoom_pan() {
+        let mut camera = Camera::default();
+        camera.zoom(2.0);
+        settle(&mut camera);
+        assert_relative_eq!(camera.distance, 0.75, epsilon = 1e-9);
+        camera.zoom(1e-6);
+        settle(&mut camera);
+        assert_relative_eq!(camera.distance, MAX_DISTANCE, epsilon = 1e-9);
+
+        // a drag of the canvas height moves the target by the height of the view
+        let mut camera = Camera {
+            follow: Follow::Root,
+            ..Camera::default()
+        };
+        camera.pan((0.0, 1.0));
+        assert_eq!(camera.follow, Follow::Free);
+        assert_relative_eq!(camera.target, vec3(0.0, 3.0, 0.0), epsilon = 1e-9);
+        camera.pan((0.5, 0.0));
+        settle(&mut camera);
+        assert_relative_eq!(camera.target, vec3(-1.5, 3.0, 0.0), epsilon = 1e-9);
+    }
+
+    #[test]
+    fn test_track() {
+        let root = vec3(0.0, 1.0, 0.0);
+        let position = [vec3(0.0, 0.0, 0.0), vec3(1.0, 0.0, 0.0)];
+        let mass = [3.0, 1.0];
+        let tracked = |follow| {
+            let mut camera = Camera {
+                follow,
+                ..Camera::default()
+            };
+            camera.track(root, &position, &mass, 1.0);
+            settle(&mut camera);
+            camera
+        };
+        assert_relative_eq!(tracked(Follow::Free).target, Vector3::zero());
+        assert_relative_eq!(tracked(Follow::Root).target, root, epsilon = 1e-9);
+        assert_relative_eq!(
+            tracked(Follow::CenterOfMass).target,
+            vec3(0.25, 0.0, 0.0),
+            epsilon = 1e-9
+        );
+        assert_relative_eq!(tracked(Follow::LastBob).target, position[1], epsilon = 1e-9);
+
+        let camera = tracked(Follow::Chain);
+        assert_relative_eq!(camera.target, vec3(0.5, 0.5, 0.0), epsilon = 1e-9);
+        // the sphere around the chain fits in the view
+        let radius = 0.5 * 2.0f64.sqrt();
+        let half = (Rad::from(Deg(camera.fov)).0 / 2.0).tan();
+        assert!(radius / camera.distance < half, "{}", camera.distance);
+        // and more so on a narrow canvas
+        let mut narrow = camera.clone();
+        narrow.track(root, &position, &mass, 0.5);
+        settle(&mut narrow);
+        assert!(narrow.distance > camera.distance * 1.5);
+    }
+
+    #[test]
+    fn test_import_without_motion() {
+        let json = r#"{"rotation":{"v":{"x":0.0,"y":0.0,"z":0.0},"s":1.0},
+            "target":{"x":0.0,"y":1.0,"z":0.0},"distance":2.0,"fov":60.0}"#;
+        let mut camera: Camera = serde_json::from_str(json).unwrap();
+        assert_eq!(camera.follow, Follow::Free);
+        settle(&mut camera);
+        assert_relative_eq!(camera.target, vec3(0.0, 1.0, 0.0));
+        assert_relative_eq!(camera.distance, 2.0);
+
+        let mut moving = Camera::default();
+        moving.zoom(2.0);
+        moving.orbit((0.1, 0.0), 0.1);
+        let json = serde_json::to_string(&moving).unwrap();
+        assert_eq!(serde_json::from_str::<Camera>(&json).unwrap(), moving);
+    }
+}
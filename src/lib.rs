@@ -1,46 +1,98 @@
 use asset_utils::make_grid;
-use cgmath::{
-    perspective, vec3, vec4, Deg, InnerSpace, Matrix, Matrix3, Matrix4, MetricSpace, Quaternion,
-    Rotation, Rotation3, SquareMatrix, Vector3,
-};
-use eom_sim::runge_kutta::RK4;
+use cgmath::{vec3, vec4, InnerSpace, Matrix4, Quaternion, Rotation, SquareMatrix, Vector3};
 use itertools::Itertools;
-use num_traits::{One, Zero};
-use pendulum::Pendulum;
+use pendulum::{HermiteLimits, Integrator, Recorder, Snapshot};
 use wasm_bindgen::prelude::*;
 use web_sys::{console, HtmlCanvasElement};
 
-pub use crate::user_input::Mouse;
+pub use crate::user_input::Pointers;
 use crate::{
-    renderer::{Backend, Object},
-    user_input::MouseButton,
+    camera::Follow,
+    pose::Pose,
+    renderer::{Backend, Object, Ribbons, ShadowMode},
+    replay::{Event, Frame, InputLog, Replay},
+    session::Session,
 };
 
+mod camera;
+mod picking;
+mod pose;
 mod renderer;
+mod replay;
+mod session;
+mod trail;
 mod user_input;
 
+/// Impulse of a flick per unit mass, per unit length dragged [1/s]
+const FLICK_GAIN: f64 = 10.0;
+
+/// Zoom by the wheel, in e-folds per pixel scrolled
+const WHEEL_ZOOM: f64 = 1e-3;
+
+/// Radius around the root and the bobs in which the pointer picks them [m]
+const PICK_RADIUS: f64 = 0.03;
+
+/// Simulated time of one physics step, independent of the frame rate [s]
+const PHYSICS_STEP: f64 = 1.0 / 60.0;
+
+/// Wall-clock time a single frame catches up at most, in physics steps at the normal rate
+///
+/// The rest is dropped e.g. after the tab was in the background.
+/// It is multiplied by the time scale in simulated time, so fast motion keeps its rate.
+const MAX_CATCH_UP_STEPS: u32 = 15;
+
+/// Default number of integration steps per physics step
+const SUBSTEPS: u32 = 256;
+
+/// Integration steps a frame runs at most, over all its physics steps
+///
+/// Fast motion and catching up are slowed down beyond it rather than stalling the page:
+/// a step of the default chain takes about 1.3 µs natively, so a frame takes some 5 ms.
+const MAX_FRAME_SUBSTEPS: u32 = 4096;
+
+/// Radius of the drawn bobs [m]
+const BOB_RADIUS: f64 = 0.02;
+
+/// Default number of physics steps kept in the trail of each bob
+const TRAIL_LENGTH: usize = 120;
+
+/// Width of the trails [m]
+const TRAIL_WIDTH: f64 = 0.01;
+
+/// Range of `App::set_time_scale`
+const MIN_TIME_SCALE: f64 = 0.1;
+const MAX_TIME_SCALE: f64 = 10.0;
+
 #[allow(dead_code)]
 pub fn log(s: String) {
     console::log_1(&s.into());
 }
 
+/// Part of `wall` seconds since the last frame that is simulated
+fn catch_up(wall: f64) -> f64 {
+    wall.min(MAX_CATCH_UP_STEPS as f64 * PHYSICS_STEP)
+}
+
 #[wasm_bindgen]
 pub struct App {
     // GL
     backend: Backend,
     sphere: Object,
+    selected_sphere: Object,
     cylinder: Object,
     floor: Object,
-    // UI
-    quaternion: Quaternion<f64>,
-    grab: bool,
-    // physics
-    pendulum: Pendulum,
-    root_position: Vector3<f64>,
-    root_velocity: Vector3<f64>,
-    position: Vec<Vector3<f64>>,
-    velocity: Vec<Vector3<f64>>,
-    last_tick: Option<f64>,
+    ribbons: Ribbons,
+    shadow_mode: ShadowMode,
+    // simulation
+    session: Session,
+    paused: bool,
+    time_scale: f64,
+    pending_step: f64,
+    // input log
+    input_log: Option<InputLog>,
+    /// calls since the last frame, logged with the next one
+    events: Vec<Event>,
+    replay: Option<Replay>,
 }
 
 #[wasm_bindgen]
@@ -50,6 +102,8 @@ impl App {
         let backend = Backend::new(canvas)?;
         let sphere =
             backend.make_from_obj(include_str!("assets/ico_sphere.obj"), [0.9, 0.4, 0.4, 1.0])?;
+        let selected_sphere =
+            backend.make_from_obj(include_str!("assets/ico_sphere.obj"), [1.0, 0.8, 0.1, 1.0])?;
         let cylinder =
             backend.make_from_obj(include_str!("assets/cylinder.obj"), [0.1, 0.9, 0.1, 1.0])?;
         let floor = {
@@ -62,153 +116,513 @@ impl App {
                 [0.8, 0.8, 1.0, 1.0],
                 [1.0, 1.0, 0.8, 1.0],
             );
-            backend.make_object(&v, &e)?
+            backend.make_object(&v, &e)?.without_shadow()
         };
-
-        let g = vec3(0.0, 9.8, 0.0);
-        let root = Vector3::zero();
-        let length_mass = vec![(0.3, 1.0); 4];
-        let pendulum = Pendulum::new(g, &length_mass).map_err(|s| JsValue::from_str(&s))?;
-        let mut position = Vec::with_capacity(length_mass.len());
-        let mut direction = vec3(0.0, -1.0, 0.0).normalize();
-        for &(l, _) in length_mass.iter() {
-            let last = position.last().copied().unwrap_or(root);
-            position.push(last + direction * l);
-            direction = Matrix3::from_angle_y(Deg(30.0)) * direction;
-        }
+        let ribbons = backend.make_ribbons()?;
+        let session = Session::new().map_err(|s| JsValue::from_str(&s))?;
 
         Ok(App {
             // GL
             backend,
             sphere,
+            selected_sphere,
             cylinder,
             floor,
-            // UI
-            quaternion: Quaternion::one(),
-            grab: false,
-            // physics
-            pendulum,
-            root_position: root,
-            root_velocity: Vector3::zero(),
-            position,
-            velocity: vec![Vector3::zero(); length_mass.len()],
-            last_tick: None,
+            ribbons,
+            shadow_mode: ShadowMode::default(),
+            // simulation
+            session,
+            paused: false,
+            time_scale: 1.0,
+            pending_step: 0.0,
+            input_log: None,
+            events: Vec::new(),
+            replay: None,
         })
     }
 
+    /// Advance to `timestamp_ms` with `pointers`, or to the next frame while replaying
     #[wasm_bindgen]
-    pub fn tick(&mut self, timestamp_ms: f64, mouse: &Mouse) -> Result<(), JsValue> {
-        let width = 1000;
-        let height = 1000;
-        self.backend.set_size(width, height);
+    pub fn tick(&mut self, timestamp_ms: f64, pointers: &mut Pointers) -> Result<(), JsValue> {
+        let result = self.next_frame(timestamp_ms, pointers);
+        // moves are measured frame by frame, also while replaying
+        pointers.end_frame();
+        result
+    }
 
-        let t = timestamp_ms / 1000.0;
-        let last_tick = self.last_tick.replace(t).unwrap_or(t);
-        if t == last_tick {
-            return Ok(());
-        }
+    #[wasm_bindgen]
+    pub fn potential_energy(&self) -> f64 {
+        self.session
+            .pendulum
+            .potential_energy(&self.session.position)
+    }
 
-        let m = Matrix3::from(self.quaternion).transpose();
-
-        if let Some((x, y)) = mouse.drag(MouseButton::Middle) {
-            if x != 0 || y != 0 {
-                let x = x as f64 / width as f64;
-                let y = y as f64 / width as f64;
-                let axis = m.y * x + m.x * y;
-                self.quaternion = self.quaternion
-                    * Quaternion::from_axis_angle(
-                        axis.normalize(),
-                        Deg(360.0 * (x * x + y * y).sqrt()),
-                    );
-            }
+    #[wasm_bindgen]
+    pub fn kinetic_energy(&self) -> f64 {
+        self.session.pendulum.kinetic_energy(&self.session.velocity)
+    }
+
+    #[wasm_bindgen]
+    pub fn unit_energy(&self) -> f64 {
+        self.session.pendulum.unit_energy()
+    }
+
+    #[wasm_bindgen]
+    pub fn export_snapshot(&self) -> Result<String, JsValue> {
+        self.snapshot()?
+            .to_json()
+            .map_err(|err| JsValue::from_str(&err))
+    }
+
+    #[wasm_bindgen]
+    pub fn export_snapshot_binary(&self) -> Result<Vec<u8>, JsValue> {
+        self.snapshot()?
+            .to_bytes()
+            .map_err(|err| JsValue::from_str(&err))
+    }
+
+    #[wasm_bindgen]
+    pub fn restore_snapshot(&mut self, data: &str) -> Result<(), JsValue> {
+        let snapshot = Snapshot::from_json(data).map_err(|err| JsValue::from_str(&err))?;
+        self.apply(Event::Restore(snapshot))
+    }
+
+    #[wasm_bindgen]
+    pub fn restore_snapshot_binary(&mut self, data: &[u8]) -> Result<(), JsValue> {
+        let snapshot = Snapshot::from_bytes(data).map_err(|err| JsValue::from_str(&err))?;
+        self.apply(Event::Restore(snapshot))
+    }
+
+    /// Start a new recording sampled every `interval` seconds
+    #[wasm_bindgen]
+    pub fn start_recording(&mut self, interval: f64) -> Result<(), JsValue> {
+        let recorder = Recorder::new(&self.session.pendulum, interval)
+            .map_err(|err| JsValue::from_str(&err))?;
+        self.session.recorder = Some(recorder);
+        self.session.recording = true;
+        Ok(())
+    }
+
+    /// Stop recording, the samples are kept until the next `start_recording`
+    #[wasm_bindgen]
+    pub fn stop_recording(&mut self) {
+        self.session.recording = false;
+    }
+
+    #[wasm_bindgen]
+    pub fn is_recording(&self) -> bool {
+        self.session.recording
+    }
+
+    #[wasm_bindgen]
+    pub fn recording_csv(&self) -> Result<String, JsValue> {
+        let mut buf = Vec::new();
+        self.recorder()?
+            .write_csv(&mut buf)
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        String::from_utf8(buf).map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    #[wasm_bindgen]
+    pub fn recording_binary(&self) -> Result<Vec<u8>, JsValue> {
+        let mut buf = Vec::new();
+        self.recorder()?
+            .write_binary(&mut buf)
+            .map_err(|err| JsValue::from_str(&err.to_string()))?;
+        Ok(buf)
+    }
+
+    /// Start logging input from now on
+    #[wasm_bindgen]
+    pub fn start_input_recording(&mut self) -> Result<(), JsValue> {
+        self.input_log = Some(
+            self.session
+                .input_log()
+                .map_err(|err| JsValue::from_str(&err))?,
+        );
+        self.events.clear();
+        Ok(())
+    }
+
+    /// Stop logging input and return the log as JSON
+    #[wasm_bindgen]
+    pub fn stop_input_recording(&mut self) -> Result<String, JsValue> {
+        self.input_log
+            .take()
+            .ok_or_else(|| JsValue::from_str("No input recording"))?
+            .to_json()
+            .map_err(|err| JsValue::from_str(&err))
+    }
+
+    /// Restore the state at the start of the log and replay its frames on the following `tick`s
+    #[wasm_bindgen]
+    pub fn start_replay(&mut self, data: &str) -> Result<(), JsValue> {
+        let log = InputLog::from_json(data).map_err(|err| JsValue::from_str(&err))?;
+        self.session
+            .restore_log(&log)
+            .map_err(|err| JsValue::from_str(&err))?;
+        self.input_log = None;
+        self.replay = Some(Replay::new(log.frames));
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn is_replaying(&self) -> bool {
+        self.replay.is_some()
+    }
+
+    /// Bound the root motion while dragging, `undefined` for no limit
+    #[wasm_bindgen]
+    pub fn set_root_limits(
+        &mut self,
+        max_acceleration: Option<f64>,
+        max_jerk: Option<f64>,
+    ) -> Result<(), JsValue> {
+        self.apply(Event::RootLimits(HermiteLimits {
+            max_acceleration,
+            max_jerk,
+        }))
+    }
+
+    /// Select the time integration scheme by name, e.g. `"rk4"` or `"symplectic-euler"`
+    #[wasm_bindgen]
+    pub fn set_integrator(&mut self, name: &str) -> Result<(), JsValue> {
+        let integrator = Integrator::from_name(name).map_err(|err| JsValue::from_str(&err))?;
+        self.apply(Event::Integrator(integrator))
+    }
+
+    #[wasm_bindgen]
+    pub fn integrator(&self) -> String {
+        self.session.integrator.name().to_string()
+    }
+
+    /// Names accepted by `set_integrator`
+    #[wasm_bindgen]
+    pub fn integrator_names() -> Vec<JsValue> {
+        Integrator::ALL
+            .iter()
+            .map(|i| JsValue::from_str(i.name()))
+            .collect()
+    }
+
+    /// Number of integration steps per physics step, at least 1
+    #[wasm_bindgen]
+    pub fn set_substeps(&mut self, substeps: u32) -> Result<(), JsValue> {
+        self.apply(Event::Substeps(substeps))
+    }
+
+    #[wasm_bindgen]
+    pub fn substeps(&self) -> u32 {
+        self.session.substeps
+    }
+
+    /// Simulated time [s]
+    #[wasm_bindgen]
+    pub fn time(&self) -> f64 {
+        self.session.time
+    }
+
+    #[wasm_bindgen]
+    pub fn pause(&mut self) {
+        self.paused = true;
+    }
+
+    #[wasm_bindgen]
+    pub fn resume(&mut self) {
+        self.paused = false;
+    }
+
+    #[wasm_bindgen]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Advance by `dt` seconds, a physics step by default, on the next `tick` even while paused
+    ///
+    /// The physics runs in fixed steps, so `dt` is rounded to a whole number of them, at least one.
+    /// More steps than a frame runs go on over the following `tick`s.
+    #[wasm_bindgen]
+    pub fn single_step(&mut self, dt: Option<f64>) {
+        let steps = (dt.unwrap_or(PHYSICS_STEP) / PHYSICS_STEP).round().max(1.0);
+        self.pending_step += steps * PHYSICS_STEP;
+    }
+
+    /// Simulated seconds per wall-clock second, clamped to 0.1 ~ 10
+    #[wasm_bindgen]
+    pub fn set_time_scale(&mut self, scale: f64) {
+        self.time_scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+    }
+
+    #[wasm_bindgen]
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// Rebuild the pendulum with `lengths` [m] and `masses` [kg] from the root down
+    /// and put it in the current pose
+    #[wasm_bindgen]
+    pub fn set_chain(&mut self, lengths: Vec<f64>, masses: Vec<f64>) -> Result<(), JsValue> {
+        if lengths.len() != masses.len() {
+            return Err(JsValue::from_str(&format!(
+                "expect {} masses, but got {}",
+                lengths.len(),
+                masses.len()
+            )));
         }
+        let length_mass = lengths.into_iter().zip(masses).collect();
+        self.apply(Event::Chain(length_mass))
+    }
 
-        let projection_matrix = perspective(Deg(90.0), width as f64 / height as f64, 0.1, 10000.0);
-        let view_matrix =
-            Matrix4::from_translation(vec3(0.0, 0.0, -1.5)) * Matrix4::from(self.quaternion);
-        let view_projection_matrix = projection_matrix * view_matrix;
+    /// Change the gravitational acceleration [m/s^2], the motion goes on but the recording stops
+    #[wasm_bindgen]
+    pub fn set_gravity(&mut self, x: f64, y: f64, z: f64) -> Result<(), JsValue> {
+        self.apply(Event::Gravity(vec3(x, y, z)))
+    }
 
-        let disp2model = |(x, y)| -> Vector3<f64> {
-            let (x, y) = (
-                x as f64 / width as f64 * 2.0 - 1.0,
-                1.0 - y as f64 / height as f64 * 2.0,
-            );
-            let root_in_display = view_projection_matrix
-                * vec4(
-                    self.root_position.x,
-                    self.root_position.y,
-                    self.root_position.z,
-                    1.0,
-                );
-            let v = view_projection_matrix.invert().unwrap()
-                * vec4(x, y, root_in_display.z, root_in_display.w);
-            // I don't know why this √2 factor needed.
-            vec3(v.x, v.y, v.z) * std::f64::consts::SQRT_2
+    /// Put the chain at rest in the pose named `preset`, e.g. `"hanging"` or `"zigzag"`
+    #[wasm_bindgen]
+    pub fn reset_pose(&mut self, preset: &str) -> Result<(), JsValue> {
+        let pose = Pose::from_name(preset).map_err(|err| JsValue::from_str(&err))?;
+        self.apply(Event::Pose(pose))
+    }
+
+    /// Names accepted by `reset_pose`
+    #[wasm_bindgen]
+    pub fn pose_presets() -> Vec<JsValue> {
+        Pose::ALL
+            .iter()
+            .map(|p| JsValue::from_str(p.name()))
+            .collect()
+    }
+
+    /// Index of the bob selected by a tap
+    #[wasm_bindgen]
+    pub fn selected_bob(&self) -> Option<usize> {
+        self.session.selected
+    }
+
+    /// Camera state as JSON
+    #[wasm_bindgen]
+    pub fn export_camera(&self) -> Result<String, JsValue> {
+        serde_json::to_string(&self.session.camera)
+            .map_err(|err| JsValue::from_str(&err.to_string()))
+    }
+
+    #[wasm_bindgen]
+    pub fn import_camera(&mut self, data: &str) -> Result<(), JsValue> {
+        let camera =
+            serde_json::from_str(data).map_err(|err| JsValue::from_str(&err.to_string()))?;
+        self.apply(Event::Camera(camera))
+    }
+
+    /// Back to the initial view
+    #[wasm_bindgen]
+    pub fn reset_camera(&mut self) -> Result<(), JsValue> {
+        self.apply(Event::ResetCamera)
+    }
+
+    /// Look at the world point `(x, y, z)` [m] smoothly
+    #[wasm_bindgen]
+    pub fn focus_camera(&mut self, x: f64, y: f64, z: f64) -> Result<(), JsValue> {
+        self.apply(Event::Focus(vec3(x, y, z)))
+    }
+
+    /// Look at the selected bob, or the root without selection
+    #[wasm_bindgen]
+    pub fn focus_selected(&mut self) -> Result<(), JsValue> {
+        let point = match self.session.selected {
+            Some(i) => self.session.position[i],
+            None => self.session.root_position,
         };
+        self.apply(Event::Focus(point))
+    }
 
-        let root_end = if let Some(p) = mouse.click(MouseButton::Left) {
-            let click_in_model = disp2model(p);
-            if self.root_position.distance2(click_in_model) <= 1e-3 {
-                self.grab = true;
-            }
-            if self.grab {
-                disp2model(mouse.current_position().unwrap_or(p))
-            } else {
-                self.root_position
+    /// Keep the camera on `"root"`, `"center-of-mass"`, `"last-bob"` or the whole `"chain"`,
+    /// or leave it `"free"`
+    #[wasm_bindgen]
+    pub fn set_camera_follow(&mut self, mode: &str) -> Result<(), JsValue> {
+        let follow = Follow::from_name(mode).map_err(|err| JsValue::from_str(&err))?;
+        self.apply(Event::Follow(follow))
+    }
+
+    #[wasm_bindgen]
+    pub fn camera_follow(&self) -> String {
+        self.session.camera.follow.name().to_string()
+    }
+
+    /// Names accepted by `set_camera_follow`
+    #[wasm_bindgen]
+    pub fn camera_follow_modes() -> Vec<JsValue> {
+        Follow::ALL
+            .iter()
+            .map(|f| JsValue::from_str(f.name()))
+            .collect()
+    }
+
+    /// Vertical field of view in degrees, clamped to 10 ~ 150
+    #[wasm_bindgen]
+    pub fn set_fov(&mut self, degree: f64) -> Result<(), JsValue> {
+        self.apply(Event::Fov(degree))
+    }
+
+    /// Number of physics steps kept in the trail of each bob, 0 to hide the trails
+    #[wasm_bindgen]
+    pub fn set_trail_length(&mut self, length: u32) {
+        self.session.trails.set_length(length as usize);
+    }
+
+    #[wasm_bindgen]
+    pub fn trail_length(&self) -> u32 {
+        self.session.trails.length() as u32
+    }
+
+    /// Colour of the trail of the `bob`-th bob from the root, `a` = 0 to hide it
+    #[wasm_bindgen]
+    pub fn set_trail_color(
+        &mut self,
+        bob: usize,
+        r: f32,
+        g: f32,
+        b: f32,
+        a: f32,
+    ) -> Result<(), JsValue> {
+        self.session
+            .trails
+            .set_color(bob, [r, g, b, a])
+            .map_err(|err| JsValue::from_str(&err))
+    }
+
+    /// Cast shadows by a `"map"` rendered from the light, cheap `"blob"`s or turn them `"off"`
+    #[wasm_bindgen]
+    pub fn set_shadow_mode(&mut self, mode: &str) -> Result<(), JsValue> {
+        self.shadow_mode = ShadowMode::from_name(mode).map_err(|err| JsValue::from_str(&err))?;
+        Ok(())
+    }
+
+    #[wasm_bindgen]
+    pub fn shadow_mode(&self) -> String {
+        self.shadow_mode.name().to_string()
+    }
+
+    /// Names accepted by `set_shadow_mode`
+    #[wasm_bindgen]
+    pub fn shadow_modes() -> Vec<JsValue> {
+        ShadowMode::ALL
+            .iter()
+            .map(|m| JsValue::from_str(m.name()))
+            .collect()
+    }
+}
+
+impl App {
+    /// Take the input of this frame, live or from the replay, and step
+    fn next_frame(&mut self, timestamp_ms: f64, pointers: &Pointers) -> Result<(), JsValue> {
+        if let Some(replay) = self.replay.as_mut() {
+            match replay.next_frame() {
+                Some(frame) => return self.step(&frame),
+                None => {
+                    // back to live input, whose clock differs from the log
+                    self.replay = None;
+                    self.session.last_tick = None;
+                }
             }
-        } else {
-            self.grab = false;
-            self.root_position
+        }
+        let wall = self.session.elapsed(timestamp_ms);
+        let max_steps = (MAX_FRAME_SUBSTEPS / self.session.substeps).max(1);
+        let max_dt = max_steps as f64 * PHYSICS_STEP;
+        // the rest of `single_step` is left to the following frames
+        let pending = std::mem::take(&mut self.pending_step);
+        let mut dt = pending.min(max_dt);
+        self.pending_step = pending - dt;
+        if !self.paused {
+            dt += (catch_up(wall) * self.time_scale).min(max_dt - dt);
+        }
+        let mut frame = Frame {
+            timestamp_ms,
+            pointers: pointers.clone(),
+            dt,
+            size: self.backend.css_size(),
+            events: Vec::new(),
         };
+        let result = self.step(&frame);
+        if let Some(log) = self.input_log.as_mut() {
+            // already applied when called
+            frame.events = std::mem::take(&mut self.events);
+            log.frames.push(frame);
+        }
+        result
+    }
 
-        let (new_tick, new_root_position, new_root_velocity) = self.pendulum.tick(
-            &mut RK4::new(),
-            last_tick,
-            t,
-            self.root_position,
-            self.root_velocity,
-            root_end,
-            &mut self.position,
-            &mut self.velocity,
-        );
-        self.last_tick = Some(new_tick);
-        self.root_position = new_root_position;
-        self.root_velocity = new_root_velocity;
+    /// Run `frame` through the session and draw
+    fn step(&mut self, frame: &Frame) -> Result<(), JsValue> {
+        let pixel_ratio = web_sys::window()
+            .map(|w| w.device_pixel_ratio())
+            .unwrap_or(1.0);
+        let aspect = self.backend.fit_canvas(pixel_ratio);
+        let (root, position) = self
+            .session
+            .play(frame)
+            .map_err(|err| JsValue::from_str(&err))?;
 
+        let camera = &self.session.camera;
+        let view_projection_matrix = camera.projection_matrix(aspect) * camera.view_matrix();
+        let (vertices, strips) =
+            self.session
+                .trails
+                .ribbons(&position, camera.toward_eye(), TRAIL_WIDTH);
+        self.ribbons.update(&vertices, &strips);
+        let casters = std::iter::once((root, 0.0))
+            .chain(position.iter().map(|&p| (p, BOB_RADIUS)))
+            .collect::<Vec<_>>();
         self.backend.draw(
             view_projection_matrix,
             vec3(1.0, 1.0, 0.0),
-            &self.calc_objects_matrix(),
+            self.shadow_mode,
+            &casters,
+            &self.calc_objects_matrix(root, &position),
+            &[&self.ribbons],
         );
         Ok(())
     }
 
-    #[wasm_bindgen]
-    pub fn potential_energy(&self) -> f64 {
-        self.pendulum.potential_energy(&self.position)
+    fn snapshot(&self) -> Result<Snapshot, JsValue> {
+        self.session
+            .snapshot()
+            .map_err(|err| JsValue::from_str(&err))
     }
 
-    #[wasm_bindgen]
-    pub fn kinetic_energy(&self) -> f64 {
-        self.pendulum.kinetic_energy(&self.velocity)
+    fn recorder(&self) -> Result<&Recorder, JsValue> {
+        self.session
+            .recorder
+            .as_ref()
+            .ok_or_else(|| JsValue::from_str("No recording"))
     }
 
-    #[wasm_bindgen]
-    pub fn unit_energy(&self) -> f64 {
-        self.pendulum.unit_energy()
+    /// Change the session by `event` now, and log it while recording input
+    fn apply(&mut self, event: Event) -> Result<(), JsValue> {
+        self.session
+            .apply(&event)
+            .map_err(|err| JsValue::from_str(&err))?;
+        if self.input_log.is_some() {
+            self.events.push(event);
+        }
+        Ok(())
     }
-}
 
-impl App {
-    fn calc_objects_matrix(&self) -> [(&Object, Vec<Matrix4<f64>>); 3] {
-        let mut position = vec![self.root_position];
-        position.extend_from_slice(&self.position);
+    fn calc_objects_matrix(
+        &self,
+        root: Vector3<f64>,
+        bobs: &[Vector3<f64>],
+    ) -> [(&Object, Vec<Matrix4<f64>>); 4] {
+        let mut position = vec![root];
+        position.extend_from_slice(bobs);
 
         let global_scale = 0.05;
 
         let mut sphere_mat = Vec::new();
+        let mut selected_mat = Vec::new();
         let mut cylinder_mat = Vec::new();
-        for (&a, &b) in position.iter().tuple_windows() {
+        for (i, (&a, &b)) in position.iter().tuple_windows().enumerate() {
             let ab = b - a;
             // cylinder's top is (0, 1, 0), bottom is (0, -1, 0).
             let rot = Matrix4::from(Quaternion::between_vectors(
@@ -224,13 +638,19 @@ impl App {
             let t = a - vec3(t.x, t.y, t.z);
             cylinder_mat.push(Matrix4::from_translation(t) * rot * scale);
 
-            let scale = Matrix4::from_scale(0.4 * global_scale);
-            sphere_mat.push(Matrix4::from_translation(b) * rot * scale);
+            let scale = Matrix4::from_scale(BOB_RADIUS);
+            let mat = Matrix4::from_translation(b) * rot * scale;
+            if self.session.selected == Some(i) {
+                selected_mat.push(mat);
+            } else {
+                sphere_mat.push(mat);
+            }
         }
         [
             (&self.floor, vec![Matrix4::identity()]),
             (&self.cylinder, cylinder_mat),
             (&self.sphere, sphere_mat),
+            (&self.selected_sphere, selected_mat),
         ]
     }
 }
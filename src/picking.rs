@@ -0,0 +1,175 @@
+use cgmath::{vec3, vec4, InnerSpace, Matrix4, SquareMatrix, Vector3};
+
+/// Half line in the world space
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Ray {
+    pub origin: Vector3<f64>,
+    /// unit vector
+    pub direction: Vector3<f64>,
+}
+
+/// Plane through `point` with the unit `normal`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DragPlane {
+    pub point: Vector3<f64>,
+    pub normal: Vector3<f64>,
+}
+
+impl Ray {
+    /// Ray from the eye through the pixel `(x, y)` of a `width` x `height` canvas
+    ///
+    /// `view_projection` must be invertible.
+    pub fn from_screen(
+        (x, y): (i32, i32),
+        width: u32,
+        height: u32,
+        view_projection: Matrix4<f64>,
+    ) -> Ray {
+        let x = (x as f64 + 0.5) / width as f64 * 2.0 - 1.0;
+        let y = 1.0 - (y as f64 + 0.5) / height as f64 * 2.0;
+        let inverse = view_projection.invert().unwrap();
+        let unproject = |z: f64| {
+            let p = inverse * vec4(x, y, z, 1.0);
+            vec3(p.x, p.y, p.z) / p.w
+        };
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+        Ray {
+            origin: near,
+            direction: (far - near).normalize(),
+        }
+    }
+
+    pub fn at(&self, s: f64) -> Vector3<f64> {
+        self.origin + self.direction * s
+    }
+
+    /// Distance to the nearest intersection with the sphere, if any in front
+    pub fn intersect_sphere(&self, center: Vector3<f64>, radius: f64) -> Option<f64> {
+        let oc = self.origin - center;
+        let b = oc.dot(self.direction);
+        let d = b * b - (oc.magnitude2() - radius * radius);
+        if d < 0.0 {
+            return None;
+        }
+        let d = d.sqrt();
+        [-b - d, -b + d].into_iter().find(|&s| s >= 0.0)
+    }
+
+    pub fn intersect_plane(&self, plane: &DragPlane) -> Option<Vector3<f64>> {
+        let denominator = self.direction.dot(plane.normal);
+        if denominator.abs() < 1e-12 {
+            return None;
+        }
+        let s = (plane.point - self.origin).dot(plane.normal) / denominator;
+        if s < 0.0 {
+            return None;
+        }
+        Some(self.at(s))
+    }
+}
+
+impl DragPlane {
+    /// Plane through `point` facing the camera of `view`
+    pub fn facing_camera(point: Vector3<f64>, view: Matrix4<f64>) -> DragPlane {
+        // the z axis of the camera in the world space
+        let normal = vec3(view.x.z, view.y.z, view.z.z).normalize();
+        DragPlane { point, normal }
+    }
+}
+
+/// Index of the sphere hit first by `ray`
+pub fn pick_sphere<I>(ray: &Ray, centers: I, radius: f64) -> Option<usize>
+where
+    I: IntoIterator<Item = Vector3<f64>>,
+{
+    centers
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, c)| ray.intersect_sphere(c, radius).map(|s| (i, s)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{assert_relative_eq, perspective, point3, Deg};
+
+    /// Eye at `(0, 0, 2)` looking at the origin
+    fn view() -> Matrix4<f64> {
+        Matrix4::look_at_rh(
+            point3(0.0, 0.0, 2.0),
+            point3(0.0, 0.0, 0.0),
+            vec3(0.0, 1.0, 0.0),
+        )
+    }
+
+    fn projection() -> Matrix4<f64> {
+        perspective(Deg(90.0), 1.0, 0.01, 100.0)
+    }
+
+    #[test]
+    fn test_from_screen() {
+        let center = Ray::from_screen((50, 50), 101, 101, projection() * view());
+        assert_relative_eq!(center.origin, vec3(0.0, 0.0, 1.99), epsilon = 1e-9);
+        assert_relative_eq!(center.direction, vec3(0.0, 0.0, -1.0), epsilon = 1e-9);
+        // the edges of a 90 degree view are at 45 degrees
+        let right = Ray::from_screen((100, 50), 101, 101, projection() * view());
+        let angle = right.direction.angle(vec3(0.0, 0.0, -1.0));
+        assert_relative_eq!(angle.0, 45f64.to_radians(), epsilon = 1e-2);
+        assert!(right.direction.x > 0.0);
+        let top = Ray::from_screen((50, 0), 101, 101, projection() * view());
+        assert!(top.direction.y > 0.0);
+    }
+
+    #[test]
+    fn test_pick_sphere() {
+        let ray = Ray {
+            origin: vec3(0.0, 0.0, 2.0),
+            direction: vec3(0.0, 0.0, -1.0),
+        };
+        let centers = [
+            vec3(0.0, 0.0, 0.0),
+            vec3(0.01, 0.0, 1.0),
+            vec3(1.0, 0.0, 1.5),
+            vec3(0.0, 0.0, 3.0),
+        ];
+        assert_eq!(pick_sphere(&ray, centers, 0.03), Some(1));
+        assert_relative_eq!(
+            ray.intersect_sphere(centers[0], 0.03).unwrap(),
+            1.97,
+            epsilon = 1e-12
+        );
+        // behind the origin
+        assert_eq!(ray.intersect_sphere(centers[3], 0.03), None);
+        assert_eq!(pick_sphere(&ray, [centers[2], centers[3]], 0.03), None);
+        // from inside the sphere
+        assert_eq!(pick_sphere(&ray, [vec3(0.0, 0.0, 2.01)], 0.03), Some(0));
+    }
+
+    #[test]
+    fn test_drag_plane() {
+        let plane = DragPlane::facing_camera(vec3(0.3, 0.2, 0.5), view());
+        assert_relative_eq!(plane.normal, vec3(0.0, 0.0, 1.0), epsilon = 1e-12);
+        let ray = Ray::from_screen((80, 30), 101, 101, projection() * view());
+        let hit = ray.intersect_plane(&plane).unwrap();
+        assert_relative_eq!(hit.z, 0.5, epsilon = 1e-9);
+        assert_relative_eq!(
+            (hit - ray.origin).normalize(),
+            ray.direction,
+            epsilon = 1e-9
+        );
+
+        let along = Ray {
+            origin: vec3(0.0, 0.0, 2.0),
+            direction: vec3(1.0, 0.0, 0.0),
+        };
+        assert_eq!(along.intersect_plane(&plane), None);
+        let away = Ray {
+            origin: vec3(0.0, 0.0, 2.0),
+            direction: vec3(0.0, 0.0, 1.0),
+        };
+        assert_eq!(away.intersect_plane(&plane), None);
+    }
+}
@@ -0,0 +1,140 @@
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_3, PI};
+
+use cgmath::{vec3, InnerSpace, Vector3};
+use pendulum::Pendulum;
+use serde::{Deserialize, Serialize};
+
+/// Initial shape of the chain, at rest
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Pose {
+    /// Straight down along gravity
+    #[default]
+    Hanging,
+    /// Straight out to the side
+    Horizontal,
+    /// Almost straight up, falls soon
+    Inverted,
+    /// Links bent alternately to either side
+    Zigzag,
+}
+
+impl Pose {
+    pub const ALL: [Pose; 4] = [
+        Pose::Hanging,
+        Pose::Horizontal,
+        Pose::Inverted,
+        Pose::Zigzag,
+    ];
+
+    pub fn from_name(name: &str) -> Result<Pose, String> {
+        Pose::ALL
+            .into_iter()
+            .find(|p| p.name() == name)
+            .ok_or_else(|| format!("unknown pose '{}'", name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pose::Hanging => "hanging",
+            Pose::Horizontal => "horizontal",
+            Pose::Inverted => "inverted",
+            Pose::Zigzag => "zigzag",
+        }
+    }
+
+    /// Bob positions of `pendulum` hung from `root`
+    pub fn position(
+        &self,
+        pendulum: &Pendulum,
+        root: Vector3<f64>,
+    ) -> Result<Vec<Vector3<f64>>, String> {
+        let n = pendulum.length_mass().len();
+        let angles = (0..n)
+            .map(|i| match self {
+                Pose::Hanging => 0.0,
+                Pose::Horizontal => FRAC_PI_2,
+                // a tiny tilt to break the symmetry
+                Pose::Inverted => PI - 1e-3,
+                Pose::Zigzag if i % 2 == 0 => FRAC_PI_3,
+                Pose::Zigzag => -FRAC_PI_3,
+            })
+            .collect::<Vec<_>>();
+        let up = pendulum.gravity().normalize();
+        let axis = if up.x.abs() < 0.9 {
+            vec3(1.0, 0.0, 0.0)
+        } else {
+            vec3(0.0, 0.0, 1.0)
+        };
+        pendulum.pose_from_angles(root, axis, &angles)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{assert_relative_eq, Zero};
+
+    #[test]
+    fn test_name() {
+        for pose in Pose::ALL {
+            assert_eq!(Pose::from_name(pose.name()), Ok(pose));
+        }
+        assert!(Pose::from_name("upside-down").is_err());
+    }
+
+    #[test]
+    fn test_position() {
+        let root = vec3(0.1, 0.5, -0.2);
+        let lengths = [0.3, 0.2, 0.25];
+        let length_mass = lengths.map(|l| (l, 1.0));
+        let pendulum = Pendulum::new(vec3(0.0, 9.8, 0.0), &length_mass).unwrap();
+        let up = vec3(0.0, 1.0, 0.0);
+        // cos of each link from the downward direction
+        let cos = |pose: Pose| {
+            let position = pose.position(&pendulum, root).unwrap();
+            assert_eq!(position.len(), 3);
+            let mut last = root;
+            let mut side = Vector3::zero();
+            lengths
+                .iter()
+                .zip(position)
+                .enumerate()
+                .map(|(i, (&l, p))| {
+                    let link = p - last;
+                    last = p;
+                    assert_relative_eq!(link.magnitude(), l, epsilon = 1e-12);
+                    // all in a vertical plane
+                    let horizontal = link - up * link.dot(up);
+                    if i == 0 {
+                        side = horizontal;
+                    } else {
+                        assert_relative_eq!(
+                            horizontal.cross(side).magnitude(),
+                            0.0,
+                            epsilon = 1e-12
+                        );
+                    }
+                    -link.dot(up) / l
+                })
+                .collect::<Vec<_>>()
+        };
+        for c in cos(Pose::Hanging) {
+            assert_relative_eq!(c, 1.0, epsilon = 1e-12);
+        }
+        for c in cos(Pose::Horizontal) {
+            assert_relative_eq!(c, 0.0, epsilon = 1e-12);
+        }
+        for c in cos(Pose::Inverted) {
+            assert!(c < -0.99, "{}", c);
+        }
+        for c in cos(Pose::Zigzag) {
+            assert_relative_eq!(c, 0.5, epsilon = 1e-12);
+        }
+        // to either side in turn
+        let position = Pose::Zigzag.position(&pendulum, root).unwrap();
+        let first = position[0] - root;
+        let second = position[1] - position[0];
+        assert!(first.x * second.x < 0.0, "{:?} {:?}", first, second);
+    }
+}
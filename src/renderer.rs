@@ -1,16 +1,41 @@
 use std::rc::Rc;
 
 use asset_utils::{parse_obj, Vertex};
-use cgmath::{Matrix4, SquareMatrix, Vector3};
-use glow::{Context, HasContext, WebBufferKey, WebProgramKey, WebShaderKey};
+use bytemuck::{Pod, Zeroable};
+use cgmath::{ortho, vec3, EuclideanSpace, InnerSpace, Matrix4, Point3, SquareMatrix, Vector3};
+use glow::{
+    Context, HasContext, WebBufferKey, WebFramebufferKey, WebProgramKey, WebShaderKey,
+    WebTextureKey,
+};
 use memoffset::offset_of;
 use wasm_bindgen::{prelude::*, JsCast};
 use web_sys::{HtmlCanvasElement, WebGl2RenderingContext, WebGlUniformLocation};
 
+/// Side of the square depth texture rendered from the light [texel]
+const SHADOW_MAP_SIZE: i32 = 2048;
+
+/// Half the depth of the box seen from the light, beyond the casters [m]
+const SHADOW_DEPTH: f64 = 50.0;
+
+/// Spheres casting blob shadows at most, same as in fragment_shader.glsl
+const MAX_BLOBS: usize = 32;
+
+/// How the objects darken what lies behind them from the light
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum ShadowMode {
+    Off,
+    /// Soft discs behind the casting spheres, cheap for weak GPUs
+    Blob,
+    /// Depth rendered from the light, softened by percentage-closer filtering
+    #[default]
+    Map,
+}
+
 #[derive(Debug)]
 pub struct Backend {
     gl: Rc<Context>,
     canvas: HtmlCanvasElement,
+    shadow_map: ShadowMap,
 }
 
 #[derive(Debug)]
@@ -20,10 +45,63 @@ pub struct Object {
     mvp_matrix_location: WebGlUniformLocation,
     inv_matrix_location: WebGlUniformLocation,
     light_direction_location: WebGlUniformLocation,
+    model_matrix_location: WebGlUniformLocation,
+    shadow_matrix_location: WebGlUniformLocation,
+    shadow_mode_location: WebGlUniformLocation,
+    shadow_map_location: WebGlUniformLocation,
+    blobs_location: WebGlUniformLocation,
+    blob_count_location: WebGlUniformLocation,
     vertex_attrib: Vec<VertexAttrib>,
     vbo: WebBufferKey,
     ebo: WebBufferKey,
     elements: usize,
+    /// drawn into the shadow map, false for the floor which only receives shadows
+    casts_shadow: bool,
+}
+
+/// Light and shadows shared by the objects in a frame
+#[derive(Debug)]
+pub struct Shading {
+    light_direction: Vector3<f64>,
+    mode: ShadowMode,
+    /// from the world to the texture coordinates and the depth of `texture`
+    shadow_matrix: Matrix4<f64>,
+    texture: WebTextureKey,
+    /// center and radius of each sphere casting a blob shadow
+    blobs: Vec<f32>,
+}
+
+/// Depth of the shadow casters seen from the light
+#[derive(Debug)]
+struct ShadowMap {
+    gl: Rc<Context>,
+    program: WebProgramKey,
+    mvp_matrix_location: WebGlUniformLocation,
+    position: VertexAttrib,
+    framebuffer: WebFramebufferKey,
+    texture: WebTextureKey,
+}
+
+/// Vertex of a line drawn without lighting
+#[repr(C)]
+#[derive(Debug, Copy, Clone, Default, Zeroable, Pod)]
+pub struct RibbonVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+
+/// Translucent triangle strips rewritten every frame
+#[derive(Debug)]
+pub struct Ribbons {
+    gl: Rc<Context>,
+    program: WebProgramKey,
+    mvp_matrix_location: WebGlUniformLocation,
+    vertex_attrib: Vec<VertexAttrib>,
+    vbo: WebBufferKey,
+    /// size of `vbo` [byte]
+    capacity: usize,
+    /// (first, count) of each strip
+    strips: Vec<(i32, i32)>,
 }
 
 #[derive(Debug)]
@@ -34,6 +112,25 @@ struct VertexAttrib {
     offset: usize,
 }
 
+impl ShadowMode {
+    pub const ALL: [ShadowMode; 3] = [ShadowMode::Off, ShadowMode::Blob, ShadowMode::Map];
+
+    pub fn from_name(name: &str) -> Result<ShadowMode, String> {
+        ShadowMode::ALL
+            .into_iter()
+            .find(|m| m.name() == name)
+            .ok_or_else(|| format!("unknown shadow mode '{}'", name))
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ShadowMode::Off => "off",
+            ShadowMode::Blob => "blob",
+            ShadowMode::Map => "map",
+        }
+    }
+}
+
 impl Backend {
     pub fn new(canvas: HtmlCanvasElement) -> Result<Backend, JsValue> {
         let webgl = canvas
@@ -47,16 +144,37 @@ impl Backend {
             gl.clear_color(0.9, 0.9, 0.9, 1.0);
             gl.clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
         }
+        let shadow_map = ShadowMap::new(gl.clone()).map_err(|err| JsValue::from_str(&err))?;
 
-        Ok(Backend { gl, canvas })
+        Ok(Backend {
+            gl,
+            canvas,
+            shadow_map,
+        })
     }
 
-    pub fn set_size(&self, width: u32, height: u32) {
-        self.canvas.set_width(width);
-        self.canvas.set_height(height);
+    /// Size of the canvas on the page in CSS pixels, at least 1x1
+    pub fn css_size(&self) -> (u32, u32) {
+        (
+            self.canvas.client_width().max(1) as u32,
+            self.canvas.client_height().max(1) as u32,
+        )
+    }
+
+    /// Resize the drawing buffer to the CSS size times `pixel_ratio`, returns the aspect ratio
+    pub fn fit_canvas(&self, pixel_ratio: f64) -> f64 {
+        let (width, height) = self.css_size();
+        let width = ((width as f64 * pixel_ratio).round() as u32).max(1);
+        let height = ((height as f64 * pixel_ratio).round() as u32).max(1);
+        // resizing clears the buffer even to the same size
+        if self.canvas.width() != width || self.canvas.height() != height {
+            self.canvas.set_width(width);
+            self.canvas.set_height(height);
+        }
         unsafe {
             self.gl.viewport(0, 0, width as i32, height as i32);
         }
+        width as f64 / height as f64
     }
 
     pub fn make_from_obj(&self, data: &str, color: [f32; 4]) -> Result<Object, JsValue> {
@@ -74,21 +192,61 @@ impl Backend {
             .map_err(|err| JsValue::from_str(&err))
     }
 
+    pub fn make_ribbons(&self) -> Result<Ribbons, JsValue> {
+        Ribbons::new(self.gl.clone()).map_err(|err| JsValue::from_str(&err))
+    }
+
+    /// Draw `objects` lit from `light_direction` and `ribbons` over them
+    ///
+    /// `casters` are the spheres, as center and radius, which the shadows are fitted around.
     pub fn draw(
         &self,
         view_projection_matrix: Matrix4<f64>,
         light_direction: Vector3<f64>,
+        shadow_mode: ShadowMode,
+        casters: &[(Vector3<f64>, f64)],
         objects: &[(&Object, Vec<Matrix4<f64>>)],
+        ribbons: &[&Ribbons],
     ) {
+        let light_matrix = ShadowMap::light_matrix(light_direction, casters);
+        if shadow_mode == ShadowMode::Map {
+            self.shadow_map.render(light_matrix, objects);
+        }
         unsafe {
+            // the shadow pass has its own viewport
+            self.gl.viewport(
+                0,
+                0,
+                self.canvas.width() as i32,
+                self.canvas.height() as i32,
+            );
             self.gl
                 .clear(glow::COLOR_BUFFER_BIT | glow::DEPTH_BUFFER_BIT);
         }
+        // from [-1, 1] to [0, 1]
+        let bias = Matrix4::from_translation(vec3(0.5, 0.5, 0.5)) * Matrix4::from_scale(0.5);
+        let shading = Shading {
+            light_direction,
+            mode: shadow_mode,
+            shadow_matrix: bias * light_matrix,
+            texture: self.shadow_map.texture,
+            blobs: casters
+                .iter()
+                .filter(|&&(_, r)| r > 0.0)
+                .take(MAX_BLOBS)
+                .flat_map(|&(c, r)| [c.x as f32, c.y as f32, c.z as f32, r as f32])
+                .collect(),
+        };
         for (obj, mat) in objects {
-            obj.bind();
+            obj.bind(&shading);
             for mat in mat.iter() {
-                obj.draw(view_projection_matrix, *mat, light_direction);
+                obj.draw(view_projection_matrix, *mat);
             }
+            obj.unbind();
+        }
+        // translucent ones last, seen through each other
+        for r in ribbons {
+            r.draw(view_projection_matrix);
         }
         unsafe {
             self.gl.flush();
@@ -107,11 +265,17 @@ impl Object {
             include_str!("assets/vertex_shader.glsl"),
             include_str!("assets/fragment_shader.glsl"),
         )?;
-        let vbo = make_buffer(&gl, glow::ARRAY_BUFFER, bytemuck::cast_slice(&vertex_array))?;
+        let vbo = make_buffer(
+            &gl,
+            glow::ARRAY_BUFFER,
+            bytemuck::cast_slice(&vertex_array),
+            glow::STATIC_DRAW,
+        )?;
         let ebo = make_buffer(
             &gl,
             glow::ELEMENT_ARRAY_BUFFER,
             bytemuck::cast_slice(&element_array),
+            glow::STATIC_DRAW,
         )?;
 
         let vertex_attrib = vec![
@@ -144,6 +308,12 @@ impl Object {
         let mvp_matrix_location = get_uniform_location(&gl, program, "mvp_matrix")?;
         let inv_matrix_location = get_uniform_location(&gl, program, "inv_matrix")?;
         let light_direction_location = get_uniform_location(&gl, program, "light_direction")?;
+        let model_matrix_location = get_uniform_location(&gl, program, "model_matrix")?;
+        let shadow_matrix_location = get_uniform_location(&gl, program, "shadow_matrix")?;
+        let shadow_mode_location = get_uniform_location(&gl, program, "shadow_mode")?;
+        let shadow_map_location = get_uniform_location(&gl, program, "shadow_map")?;
+        let blobs_location = get_uniform_location(&gl, program, "blobs")?;
+        let blob_count_location = get_uniform_location(&gl, program, "blob_count")?;
 
         Ok(Object {
             gl,
@@ -151,16 +321,61 @@ impl Object {
             mvp_matrix_location,
             inv_matrix_location,
             light_direction_location,
+            model_matrix_location,
+            shadow_matrix_location,
+            shadow_mode_location,
+            shadow_map_location,
+            blobs_location,
+            blob_count_location,
             vertex_attrib,
             vbo,
             ebo,
             elements: element_array.len(),
+            casts_shadow: true,
         })
     }
 
-    pub fn bind(&self) {
+    /// Only receive shadows, e.g. for the floor
+    pub fn without_shadow(self) -> Object {
+        Object {
+            casts_shadow: false,
+            ..self
+        }
+    }
+
+    pub fn bind(&self, shading: &Shading) {
+        let mode = match shading.mode {
+            ShadowMode::Off => 0,
+            ShadowMode::Blob => 1,
+            ShadowMode::Map => 2,
+        };
         unsafe {
             self.gl.use_program(Some(self.program));
+            self.gl.uniform_3_f32(
+                Some(&self.light_direction_location),
+                shading.light_direction.x as f32,
+                shading.light_direction.y as f32,
+                shading.light_direction.z as f32,
+            );
+            self.gl.uniform_matrix_4_f32_slice(
+                Some(&self.shadow_matrix_location),
+                false,
+                &mat_as_vec(shading.shadow_matrix),
+            );
+            self.gl
+                .uniform_1_i32(Some(&self.shadow_mode_location), mode);
+            self.gl.active_texture(glow::TEXTURE0);
+            self.gl
+                .bind_texture(glow::TEXTURE_2D, Some(shading.texture));
+            self.gl.uniform_1_i32(Some(&self.shadow_map_location), 0);
+            if !shading.blobs.is_empty() {
+                self.gl
+                    .uniform_4_f32_slice(Some(&self.blobs_location), &shading.blobs);
+            }
+            self.gl.uniform_1_i32(
+                Some(&self.blob_count_location),
+                (shading.blobs.len() / 4) as i32,
+            );
             self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.vbo));
             self.gl
                 .bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(self.ebo));
@@ -170,22 +385,13 @@ impl Object {
         }
     }
 
-    pub fn draw(
-        &self,
-        view_projection_matrix: Matrix4<f64>,
-        model_matrix: Matrix4<f64>,
-        light_direction: Vector3<f64>,
-    ) {
-        fn mat_as_vec(mat: Matrix4<f64>) -> Vec<f32> {
-            let mut v = Vec::with_capacity(16);
-            for col in Into::<[[f64; 4]; 4]>::into(mat) {
-                for c in col {
-                    v.push(c as f32);
-                }
-            }
-            v
+    pub fn unbind(&self) {
+        for va in self.vertex_attrib.iter() {
+            va.unbind(&self.gl);
         }
+    }
 
+    pub fn draw(&self, view_projection_matrix: Matrix4<f64>, model_matrix: Matrix4<f64>) {
         let inv_matrix = model_matrix.invert().unwrap_or_else(Matrix4::identity);
         let mvp_matrix = view_projection_matrix * model_matrix;
         unsafe {
@@ -199,11 +405,10 @@ impl Object {
                 false,
                 &mat_as_vec(inv_matrix),
             );
-            self.gl.uniform_3_f32(
-                Some(&self.light_direction_location),
-                light_direction.x as f32,
-                light_direction.y as f32,
-                light_direction.z as f32,
+            self.gl.uniform_matrix_4_f32_slice(
+                Some(&self.model_matrix_location),
+                false,
+                &mat_as_vec(model_matrix),
             );
             self.gl
                 .draw_elements(glow::TRIANGLES, self.elements as i32, glow::UNSIGNED_INT, 0);
@@ -211,6 +416,237 @@ impl Object {
     }
 }
 
+impl ShadowMap {
+    fn new(gl: Rc<Context>) -> Result<ShadowMap, String> {
+        let program = make_program(
+            &gl,
+            include_str!("assets/shadow_vertex_shader.glsl"),
+            include_str!("assets/shadow_fragment_shader.glsl"),
+        )?;
+        let position = VertexAttrib::new(
+            &gl,
+            program,
+            "position",
+            3,
+            std::mem::size_of::<Vertex>(),
+            offset_of!(Vertex, position),
+        )?;
+        let mvp_matrix_location = get_uniform_location(&gl, program, "mvp_matrix")?;
+
+        unsafe {
+            let texture = gl.create_texture()?;
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.tex_storage_2d(
+                glow::TEXTURE_2D,
+                1,
+                glow::DEPTH_COMPONENT24,
+                SHADOW_MAP_SIZE,
+                SHADOW_MAP_SIZE,
+            );
+            // compare in the sampler and filter the results linearly
+            for (parameter, value) in [
+                (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_COMPARE_MODE, glow::COMPARE_REF_TO_TEXTURE),
+                (glow::TEXTURE_COMPARE_FUNC, glow::LEQUAL),
+            ] {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, parameter, value as i32);
+            }
+            gl.bind_texture(glow::TEXTURE_2D, None);
+
+            let framebuffer = gl.create_framebuffer()?;
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::DEPTH_ATTACHMENT,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            gl.draw_buffers(&[glow::NONE]);
+            gl.read_buffer(glow::NONE);
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                return Err(format!("Incomplete shadow framebuffer: {:#x}", status));
+            }
+
+            Ok(ShadowMap {
+                gl,
+                program,
+                mvp_matrix_location,
+                position,
+                framebuffer,
+                texture,
+            })
+        }
+    }
+
+    /// Orthographic view from the light enclosing `casters` and what lies behind them
+    fn light_matrix(
+        light_direction: Vector3<f64>,
+        casters: &[(Vector3<f64>, f64)],
+    ) -> Matrix4<f64> {
+        let direction = light_direction.normalize();
+        let center = if casters.is_empty() {
+            Vector3::new(0.0, 0.0, 0.0)
+        } else {
+            casters.iter().map(|&(c, _)| c).sum::<Vector3<f64>>() / casters.len() as f64
+        };
+        let radius = casters
+            .iter()
+            .map(|&(c, r)| (c - center).magnitude() + r)
+            .fold(0.0, f64::max)
+            // keep a margin for the PCF and a single caster
+            + 0.05;
+        let up = if direction.y.abs() < 0.9 {
+            vec3(0.0, 1.0, 0.0)
+        } else {
+            vec3(1.0, 0.0, 0.0)
+        };
+        let view = Matrix4::look_at_rh(
+            Point3::from_vec(center + direction * SHADOW_DEPTH),
+            Point3::from_vec(center),
+            up,
+        );
+        ortho(-radius, radius, -radius, radius, 0.0, 2.0 * SHADOW_DEPTH) * view
+    }
+
+    /// Render the depth of the objects casting shadows seen by `light_matrix`
+    fn render(&self, light_matrix: Matrix4<f64>, objects: &[(&Object, Vec<Matrix4<f64>>)]) {
+        unsafe {
+            self.gl
+                .bind_framebuffer(glow::FRAMEBUFFER, Some(self.framebuffer));
+            self.gl.viewport(0, 0, SHADOW_MAP_SIZE, SHADOW_MAP_SIZE);
+            self.gl.clear(glow::DEPTH_BUFFER_BIT);
+            self.gl.use_program(Some(self.program));
+            // push the depth back against the acne of the surfaces shadowing themselves
+            self.gl.enable(glow::POLYGON_OFFSET_FILL);
+            self.gl.polygon_offset(2.0, 4.0);
+            for (obj, mat) in objects.iter().filter(|(obj, _)| obj.casts_shadow) {
+                self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(obj.vbo));
+                self.gl
+                    .bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(obj.ebo));
+                self.position.bind(&self.gl);
+                for &mat in mat.iter() {
+                    self.gl.uniform_matrix_4_f32_slice(
+                        Some(&self.mvp_matrix_location),
+                        false,
+                        &mat_as_vec(light_matrix * mat),
+                    );
+                    self.gl.draw_elements(
+                        glow::TRIANGLES,
+                        obj.elements as i32,
+                        glow::UNSIGNED_INT,
+                        0,
+                    );
+                }
+            }
+            self.position.unbind(&self.gl);
+            self.gl.disable(glow::POLYGON_OFFSET_FILL);
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+        }
+    }
+}
+
+impl Ribbons {
+    pub fn new(gl: Rc<Context>) -> Result<Ribbons, String> {
+        let program = make_program(
+            &gl,
+            include_str!("assets/ribbon_vertex_shader.glsl"),
+            include_str!("assets/ribbon_fragment_shader.glsl"),
+        )?;
+        let vbo = make_buffer(&gl, glow::ARRAY_BUFFER, &[], glow::DYNAMIC_DRAW)?;
+
+        let vertex_attrib = vec![
+            VertexAttrib::new(
+                &gl,
+                program,
+                "position",
+                3,
+                std::mem::size_of::<RibbonVertex>(),
+                offset_of!(RibbonVertex, position),
+            )?,
+            VertexAttrib::new(
+                &gl,
+                program,
+                "color",
+                4,
+                std::mem::size_of::<RibbonVertex>(),
+                offset_of!(RibbonVertex, color),
+            )?,
+        ];
+
+        let mvp_matrix_location = get_uniform_location(&gl, program, "mvp_matrix")?;
+
+        Ok(Ribbons {
+            gl,
+            program,
+            mvp_matrix_location,
+            vertex_attrib,
+            vbo,
+            capacity: 0,
+            strips: Vec::new(),
+        })
+    }
+
+    /// Replace the strips by `vertices`, each strip as (first, count) in them
+    pub fn update(&mut self, vertices: &[RibbonVertex], strips: &[(usize, usize)]) {
+        let data: &[u8] = bytemuck::cast_slice(vertices);
+        unsafe {
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.vbo));
+            if data.len() > self.capacity {
+                // grow with some room to avoid reallocating while the trails fill up
+                let capacity = data.len().next_power_of_two();
+                self.gl
+                    .buffer_data_size(glow::ARRAY_BUFFER, capacity as i32, glow::DYNAMIC_DRAW);
+                self.capacity = capacity;
+            }
+            self.gl
+                .buffer_sub_data_u8_slice(glow::ARRAY_BUFFER, 0, data);
+        }
+        self.strips = strips
+            .iter()
+            .filter(|&&(_, count)| count >= 3)
+            .map(|&(first, count)| (first as i32, count as i32))
+            .collect();
+    }
+
+    /// Blend over what is drawn so far without hiding it
+    pub fn draw(&self, view_projection_matrix: Matrix4<f64>) {
+        if self.strips.is_empty() {
+            return;
+        }
+        unsafe {
+            self.gl.use_program(Some(self.program));
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(self.vbo));
+            for va in self.vertex_attrib.iter() {
+                va.bind(&self.gl);
+            }
+            self.gl.uniform_matrix_4_f32_slice(
+                Some(&self.mvp_matrix_location),
+                false,
+                &mat_as_vec(view_projection_matrix),
+            );
+            self.gl.enable(glow::BLEND);
+            self.gl
+                .blend_func(glow::SRC_ALPHA, glow::ONE_MINUS_SRC_ALPHA);
+            self.gl.depth_mask(false);
+            for &(first, count) in self.strips.iter() {
+                self.gl.draw_arrays(glow::TRIANGLE_STRIP, first, count);
+            }
+            self.gl.depth_mask(true);
+            self.gl.disable(glow::BLEND);
+            // attributes left enabled would be checked against the buffer of the next draw
+            for va in self.vertex_attrib.iter() {
+                va.unbind(&self.gl);
+            }
+        }
+    }
+}
+
 impl VertexAttrib {
     fn new(
         gl: &Context,
@@ -242,6 +678,12 @@ impl VertexAttrib {
             gl.enable_vertex_attrib_array(self.index);
         }
     }
+
+    fn unbind(&self, gl: &Context) {
+        unsafe {
+            gl.disable_vertex_attrib_array(self.index);
+        }
+    }
 }
 
 fn make_shader(
@@ -305,11 +747,22 @@ fn get_uniform_location(
     }
 }
 
-fn make_buffer(gl: &Context, target: u32, data: &[u8]) -> Result<WebBufferKey, String> {
+/// `usage` is `glow::STATIC_DRAW` for data set once, `glow::DYNAMIC_DRAW` for data rewritten
+fn make_buffer(gl: &Context, target: u32, data: &[u8], usage: u32) -> Result<WebBufferKey, String> {
     unsafe {
         let bo = gl.create_buffer()?;
         gl.bind_buffer(target, Some(bo));
-        gl.buffer_data_u8_slice(target, data, glow::STATIC_READ);
+        gl.buffer_data_u8_slice(target, data, usage);
         Ok(bo)
     }
 }
+
+fn mat_as_vec(mat: Matrix4<f64>) -> Vec<f32> {
+    let mut v = Vec::with_capacity(16);
+    for col in Into::<[[f64; 4]; 4]>::into(mat) {
+        for c in col {
+            v.push(c as f32);
+        }
+    }
+    v
+}
@@ -0,0 +1,222 @@
+use cgmath::Vector3;
+use pendulum::{HermiteLimits, Integrator, Snapshot, VelocityEstimator};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    camera::{Camera, Follow},
+    pose::Pose,
+    user_input::{Flick, Grab, Pointers},
+};
+
+/// Input seen by one `App::tick`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Frame {
+    pub timestamp_ms: f64,
+    pub pointers: Pointers,
+    /// Simulated interval since the previous frame
+    pub dt: f64,
+    /// CSS size of the canvas the pointers were on
+    pub size: (u32, u32),
+    /// Calls on `App` since the previous frame, applied before its input
+    pub events: Vec<Event>,
+}
+
+/// A call on `App` that changes the session between frames
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    RootLimits(HermiteLimits),
+    Integrator(Integrator),
+    Substeps(u32),
+    Chain(Vec<(f64, f64)>),
+    Gravity(Vector3<f64>),
+    Pose(Pose),
+    Camera(Camera),
+    ResetCamera,
+    Follow(Follow),
+    /// world point to look at [m]
+    Focus(Vector3<f64>),
+    /// vertical field of view [deg]
+    Fov(f64),
+    Restore(Snapshot),
+}
+
+/// Everything needed to run a session again frame-for-frame
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputLog {
+    pub snapshot: Snapshot,
+    pub last_tick: Option<f64>,
+    pub camera: Camera,
+    pub grab: Option<Grab>,
+    pub flick: Option<Flick>,
+    pub selected: Option<usize>,
+    pub integrator: Integrator,
+    pub substeps: u32,
+    pub accumulator: f64,
+    /// state before the last physics step, which the first frames are interpolated from
+    pub previous_root_position: Vector3<f64>,
+    pub previous_position: Vec<Vector3<f64>>,
+    pub root_target: VelocityEstimator,
+    pub root_limits: HermiteLimits,
+    pub frames: Vec<Frame>,
+}
+
+impl InputLog {
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string(self).map_err(|err| err.to_string())
+    }
+
+    pub fn from_json(data: &str) -> Result<InputLog, String> {
+        let log: InputLog = serde_json::from_str(data).map_err(|err| err.to_string())?;
+        if log.previous_position.len() != log.snapshot.position.len() {
+            return Err(format!(
+                "expect {} previous positions, but got {}",
+                log.snapshot.position.len(),
+                log.previous_position.len()
+            ));
+        }
+        Ok(log)
+    }
+}
+
+/// Frames of an `InputLog` being played back
+#[derive(Debug)]
+pub struct Replay {
+    frames: std::vec::IntoIter<Frame>,
+}
+
+impl Replay {
+    pub fn new(frames: Vec<Frame>) -> Replay {
+        Replay {
+            frames: frames.into_iter(),
+        }
+    }
+
+    pub fn next_frame(&mut self) -> Option<Frame> {
+        self.frames.next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{vec3, InnerSpace};
+
+    use crate::{session::Session, PHYSICS_STEP};
+
+    const SIZE: (u32, u32) = (640, 480);
+
+    /// Pixel of the canvas where `point` is seen
+    fn to_screen(session: &Session, point: Vector3<f64>) -> (i32, i32) {
+        let camera = &session.camera;
+        let aspect = SIZE.0 as f64 / SIZE.1 as f64;
+        let p = camera.projection_matrix(aspect) * camera.view_matrix() * point.extend(1.0);
+        let (x, y) = (p.x / p.w, p.y / p.w);
+        (
+            ((x + 1.0) / 2.0 * SIZE.0 as f64).floor() as i32,
+            ((1.0 - y) / 2.0 * SIZE.1 as f64).floor() as i32,
+        )
+    }
+
+    /// Flick the last bob by a finger, drag the root by the mouse, bound it on the way,
+    /// switch the integrator, change the gravity and the chain, and move the camera,
+    /// at irregular frame intervals
+    fn record(session: &mut Session) -> InputLog {
+        let mut log = session.input_log().unwrap();
+        let mut pointers = Pointers::new();
+        let mut timestamp_ms = 1000.0 / 3.0;
+        let mut at = (0, 0);
+        for i in 0..90 {
+            match i {
+                5 => {
+                    at = to_screen(session, session.position[3]);
+                    pointers.add_pointerdown(2, at.0, at.1, 0, "touch");
+                }
+                6..=9 => {
+                    at = (at.0 - 7, at.1);
+                    pointers.add_pointermove(2, at.0, at.1);
+                }
+                10 => {
+                    assert_eq!(session.flick.and_then(|f| f.bob), Some(3));
+                    pointers.add_pointerup(2, at.0, at.1);
+                }
+                20 => {
+                    at = to_screen(session, session.root_position);
+                    pointers.add_pointerdown(1, at.0, at.1, 0, "mouse");
+                }
+                21..=39 => {
+                    at = (at.0 + 3, at.1 - 2);
+                    pointers.add_pointermove(1, at.0, at.1);
+                }
+                40 => {
+                    assert!(session.grabbing());
+                    pointers.add_pointerup(1, at.0, at.1);
+                }
+                60 => pointers.add_wheel(120.0),
+                _ => (),
+            }
+            let wall = (1.0 + 0.4 * ((i * 7 % 5) as f64 - 2.0) / 3.0) / 60.0;
+            timestamp_ms += wall * 1000.0;
+            let mut frame = Frame {
+                timestamp_ms,
+                pointers: pointers.clone(),
+                dt: wall * 1.5,
+                size: SIZE,
+                events: Vec::new(),
+            };
+            // as `App` does, the calls take effect at once and are logged with the next frame
+            let events = match i {
+                30 => vec![Event::RootLimits(HermiteLimits {
+                    max_acceleration: Some(20.0),
+                    max_jerk: None,
+                })],
+                50 => vec![
+                    Event::Integrator(Integrator::SymplecticEuler),
+                    Event::Substeps(8),
+                ],
+                55 => vec![Event::Gravity(vec3(0.0, 1.6, 0.0))],
+                65 => vec![Event::Fov(60.0), Event::Focus(session.position[1])],
+                75 => vec![Event::Follow(Follow::LastBob)],
+                70 => vec![
+                    Event::Chain(vec![(0.2, 1.0), (0.4, 0.5), (0.1, 2.0)]),
+                    Event::Pose(Pose::Zigzag),
+                ],
+                _ => Vec::new(),
+            };
+            for event in events.iter() {
+                session.apply(event).unwrap();
+            }
+            session.play(&frame).unwrap();
+            frame.events = events;
+            pointers.end_frame();
+            log.frames.push(frame);
+        }
+        log
+    }
+
+    #[test]
+    fn test_replay_bitwise() {
+        let mut live = Session::new().unwrap();
+        live.substeps = 16;
+        // start in the middle of a physics step
+        live.accumulator = PHYSICS_STEP / 3.0;
+        let root = live.root_position;
+        let log = record(&mut live);
+        assert!((live.root_position - root).magnitude() > 0.05);
+        assert!(live.camera.distance > Session::new().unwrap().camera.distance);
+
+        let restored = InputLog::from_json(&log.to_json().unwrap()).unwrap();
+        let mut replayed = Session::new().unwrap();
+        replayed.restore_log(&restored).unwrap();
+        for frame in restored.frames.iter() {
+            replayed.play(frame).unwrap();
+        }
+        assert!(replayed.time == live.time);
+        assert!(replayed.accumulator == live.accumulator);
+        assert!(replayed.root_position == live.root_position);
+        assert!(replayed.root_velocity == live.root_velocity);
+        assert!(replayed.position == live.position);
+        assert!(replayed.velocity == live.velocity);
+        assert!(replayed.camera == live.camera);
+    }
+}
@@ -0,0 +1,492 @@
+use cgmath::{vec3, Vector3};
+use num_traits::Zero;
+use pendulum::{
+    Dynamics, Hermite3, HermiteLimits, Integrator, Pendulum, Recorder, Snapshot, VelocityEstimator,
+};
+
+use crate::{
+    camera::Camera,
+    picking::{pick_sphere, DragPlane, Ray},
+    pose::Pose,
+    replay::{Event, Frame, InputLog},
+    trail::Trails,
+    user_input::{Flick, Grab, PointerKind, Pointers},
+    FLICK_GAIN, PHYSICS_STEP, PICK_RADIUS, SUBSTEPS, TRAIL_LENGTH, WHEEL_ZOOM,
+};
+
+/// The simulation and the input driving it, everything of `App` but drawing
+///
+/// A replay runs frames through the same code as live input does.
+pub struct Session {
+    // UI
+    pub camera: Camera,
+    pub grab: Option<Grab>,
+    pub flick: Option<Flick>,
+    pub selected: Option<usize>,
+    pub trails: Trails,
+    pub root_target: VelocityEstimator,
+    pub root_limits: HermiteLimits,
+    // physics
+    pub pendulum: Pendulum,
+    pub root_position: Vector3<f64>,
+    pub root_velocity: Vector3<f64>,
+    pub position: Vec<Vector3<f64>>,
+    pub velocity: Vec<Vector3<f64>>,
+    pub pose: Pose,
+    /// wall-clock time of the last frame
+    pub last_tick: Option<f64>,
+    /// simulated time, runs apart from `last_tick` while paused or scaled
+    pub time: f64,
+    /// simulated time not yet run by a physics step
+    pub accumulator: f64,
+    /// state before the last physics step, to interpolate from
+    pub previous_root_position: Vector3<f64>,
+    pub previous_position: Vec<Vector3<f64>>,
+    pub integrator: Integrator,
+    pub substeps: u32,
+    pub recorder: Option<Recorder>,
+    pub recording: bool,
+}
+
+impl Session {
+    pub fn new() -> Result<Session, String> {
+        let g = vec3(0.0, 9.8, 0.0);
+        let root = Vector3::zero();
+        let length_mass = vec![(0.3, 1.0); 4];
+        let pendulum = Pendulum::new(g, &length_mass)?;
+        let pose = Pose::default();
+        let position = pose.position(&pendulum, root)?;
+
+        let mut session = Session {
+            // UI
+            camera: Camera::default(),
+            grab: None,
+            flick: None,
+            selected: None,
+            trails: Trails::new(length_mass.len(), TRAIL_LENGTH),
+            root_target: VelocityEstimator::new(0.1),
+            root_limits: HermiteLimits::default(),
+            // physics
+            pendulum,
+            root_position: root,
+            root_velocity: Vector3::zero(),
+            position,
+            velocity: vec![Vector3::zero(); length_mass.len()],
+            pose,
+            last_tick: None,
+            time: 0.0,
+            accumulator: 0.0,
+            previous_root_position: root,
+            previous_position: Vec::new(),
+            integrator: Integrator::default(),
+            substeps: SUBSTEPS,
+            recorder: None,
+            recording: false,
+        };
+        session.previous_position.clone_from(&session.position);
+        Ok(session)
+    }
+
+    /// The root is dragged by a pointer
+    pub fn grabbing(&self) -> bool {
+        self.grab.is_some_and(|g| g.root)
+    }
+
+    /// Wall-clock seconds from the last frame to `timestamp_ms`
+    pub fn elapsed(&self, timestamp_ms: f64) -> f64 {
+        let t = timestamp_ms / 1000.0;
+        t - self.last_tick.unwrap_or(t)
+    }
+
+    /// Run the events and the input of `frame`
+    ///
+    /// Returns the root and the bobs to draw, interpolated past the last physics step.
+    pub fn play(&mut self, frame: &Frame) -> Result<(Vector3<f64>, Vec<Vector3<f64>>), String> {
+        for event in frame.events.iter() {
+            self.apply(event)?;
+        }
+        let wall = self.elapsed(frame.timestamp_ms);
+        self.last_tick = Some(frame.timestamp_ms / 1000.0);
+        self.step(wall, frame.dt, &frame.pointers, frame.size)
+    }
+
+    /// Simulate `dt` seconds with `pointers` on a canvas of `size` in CSS pixels,
+    /// `wall` seconds after the last frame
+    fn step(
+        &mut self,
+        wall: f64,
+        dt: f64,
+        pointers: &Pointers,
+        (width, height): (u32, u32),
+    ) -> Result<(Vector3<f64>, Vec<Vector3<f64>>), String> {
+        // pick with the view on the screen
+        let view_matrix = self.camera.view_matrix();
+        let view_projection_matrix =
+            self.camera.projection_matrix(width as f64 / height as f64) * view_matrix;
+        let ray = |p| Ray::from_screen(p, width, height, view_projection_matrix);
+        // where the pointer at `p` drags `at` on the plane facing the camera
+        let drag_to = |p, at| {
+            ray(p)
+                .intersect_plane(&DragPlane::facing_camera(at, view_matrix))
+                .unwrap_or(at)
+        };
+
+        // tap a bob to select it, elsewhere to deselect
+        if let Some(p) = pointers.iter().find(|p| p.button == 0 && p.is_tap()) {
+            self.selected =
+                pick_sphere(&ray(p.current), self.position.iter().copied(), PICK_RADIUS);
+        }
+
+        // flick a bob by the right button or a finger to hit it, once it is not a tap
+        if self.flick.is_none() {
+            let flicking = pointers
+                .mouse(2)
+                .or_else(|| pointers.primary().filter(|p| p.kind != PointerKind::Mouse))
+                .filter(|p| p.is_drag());
+            if let Some(p) = flicking {
+                let bob = pick_sphere(&ray(p.start), self.position.iter().copied(), PICK_RADIUS);
+                self.flick = Some(Flick {
+                    pointer: p.id,
+                    start: p.start,
+                    bob,
+                });
+            }
+        }
+        let mut panning = None;
+        if let Some(flick) = self.flick {
+            match pointers.get(flick.pointer) {
+                Some(p) if p.released => {
+                    self.flick = None;
+                    if let Some(i) = flick.bob {
+                        let at = self.position[i];
+                        let drag = drag_to(p.current, at) - drag_to(flick.start, at);
+                        let mass = self.pendulum.length_mass()[i].1;
+                        self.pendulum.apply_impulse(
+                            self.time,
+                            i,
+                            drag * (mass * FLICK_GAIN),
+                            &self.position,
+                            &mut self.velocity,
+                        )?;
+                    }
+                }
+                Some(p) if flick.bob.is_none() => panning = Some(p.delta()),
+                Some(_) => (),
+                None => self.flick = None,
+            }
+        }
+
+        // drag the root by the left button or a finger
+        let root_end = match pointers.primary().filter(|p| !p.released) {
+            Some(p) => {
+                // only where it was pressed, the root may swing under a held pointer later
+                if self.grab.map(|g| g.pointer) != Some(p.id) {
+                    let root = ray(p.start)
+                        .intersect_sphere(self.root_position, PICK_RADIUS)
+                        .is_some();
+                    if root {
+                        self.root_target.clear();
+                    }
+                    self.grab = Some(Grab {
+                        pointer: p.id,
+                        root,
+                    });
+                }
+                if self.grabbing() {
+                    drag_to(p.current, self.root_position)
+                } else {
+                    self.root_position
+                }
+            }
+            None => {
+                self.grab = None;
+                self.root_position
+            }
+        };
+
+        // orbit by the middle button or two fingers, zoom by the wheel or pinching,
+        // and pan by dragging off the bobs
+        let (width, height) = (width as f64, height as f64);
+        if let Some(p) = pointers.mouse(1) {
+            let (x, y) = p.delta();
+            self.camera
+                .orbit((x as f64 / width, y as f64 / width), wall);
+        }
+        match pointers.gesture() {
+            Some(gesture) => {
+                let (x, y) = gesture.pan;
+                self.camera.orbit((x / width, y / width), wall);
+                self.camera.zoom(gesture.scale);
+                self.camera.roll(gesture.twist);
+            }
+            None if !self.grabbing() => {
+                if let Some((x, y)) = panning.filter(|&d| d != (0, 0)) {
+                    self.camera.pan((x as f64 / height, y as f64 / height));
+                }
+            }
+            None => (),
+        }
+        self.camera.zoom((-pointers.wheel() * WHEEL_ZOOM).exp());
+
+        self.accumulator += dt;
+        // whole steps from `single_step` must not be lost to rounding
+        let steps = (self.accumulator / PHYSICS_STEP + 1e-9).floor() as u32;
+        self.accumulator = (self.accumulator - steps as f64 * PHYSICS_STEP).max(0.0);
+        if steps > 0 {
+            let t = self.time + steps as f64 * PHYSICS_STEP;
+            let root_end_velocity = if self.grabbing() {
+                self.root_target.push(t, root_end);
+                self.root_target.estimate()
+            } else {
+                Vector3::zero()
+            };
+
+            let root = Hermite3::with_limits(
+                self.root_position,
+                self.root_velocity,
+                root_end,
+                root_end_velocity,
+                self.time,
+                t,
+                &self.root_limits,
+            );
+            self.pendulum.set_root(root);
+            for _ in 0..steps {
+                self.previous_root_position = root.x(self.time);
+                self.previous_position.clone_from(&self.position);
+                // the trails end behind the bobs drawn between the last two steps
+                self.trails.push(&self.previous_position);
+                self.time = self.pendulum.advance_with(
+                    self.integrator,
+                    self.recorder.as_mut().filter(|_| self.recording),
+                    self.time,
+                    self.time + PHYSICS_STEP,
+                    PHYSICS_STEP / self.substeps as f64,
+                    &mut self.position,
+                    &mut self.velocity,
+                );
+            }
+            self.root_position = root.x(self.time);
+            self.root_velocity = root.v(self.time);
+            // until the next steps, so that impulses and snapshots see where the root is
+            self.pendulum
+                .set_free_root(self.time, self.root_position, self.root_velocity);
+        }
+
+        let (root, position) = self.interpolated(self.accumulator / PHYSICS_STEP);
+        let mass = self
+            .pendulum
+            .length_mass()
+            .iter()
+            .map(|&(_, m)| m)
+            .collect::<Vec<_>>();
+        // as on the screen the input was given on, which the replay has to see the same
+        self.camera.track(root, &position, &mass, width / height);
+        self.camera.update(wall);
+        Ok((root, position))
+    }
+
+    /// Make a change asked for between frames
+    pub fn apply(&mut self, event: &Event) -> Result<(), String> {
+        match event {
+            Event::RootLimits(limits) => self.root_limits = *limits,
+            Event::Integrator(integrator) => self.integrator = *integrator,
+            Event::Substeps(substeps) => self.substeps = (*substeps).max(1),
+            Event::Chain(length_mass) => self.set_chain(length_mass)?,
+            Event::Gravity(g) => {
+                self.pendulum.set_gravity(self.time, *g)?;
+                // samples under another gravity cannot be mixed in
+                self.recording = false;
+            }
+            Event::Pose(pose) => self.reset_pose(*pose)?,
+            Event::Camera(camera) => self.camera = camera.clone(),
+            Event::ResetCamera => self.camera.reset(),
+            Event::Follow(follow) => self.camera.follow = *follow,
+            Event::Focus(point) => self.camera.focus(*point),
+            Event::Fov(degree) => self.camera.fov = degree.clamp(10.0, 150.0),
+            Event::Restore(snapshot) => self.restore(snapshot.clone())?,
+        }
+        Ok(())
+    }
+
+    /// Rebuild the pendulum with `length_mass` and put it in the current pose
+    fn set_chain(&mut self, length_mass: &[(f64, f64)]) -> Result<(), String> {
+        let pendulum = Pendulum::new(self.pendulum.gravity(), length_mass)?;
+        let position = self.pose.position(&pendulum, self.root_position)?;
+        let velocity = vec![Vector3::zero(); position.len()];
+        self.pendulum = pendulum;
+        self.root_velocity = Vector3::zero();
+        self.reset_state(position, velocity);
+        // samples of another chain cannot be mixed in
+        self.recording = false;
+        Ok(())
+    }
+
+    /// Put the chain at rest in `pose`
+    fn reset_pose(&mut self, pose: Pose) -> Result<(), String> {
+        let position = pose.position(&self.pendulum, self.root_position)?;
+        let velocity = vec![Vector3::zero(); position.len()];
+        self.pose = pose;
+        self.root_velocity = Vector3::zero();
+        self.reset_state(position, velocity);
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> Result<Snapshot, String> {
+        Snapshot::new(
+            &self.pendulum,
+            self.time,
+            self.root_position,
+            self.root_velocity,
+            &self.position,
+            &self.velocity,
+        )
+    }
+
+    pub fn restore(&mut self, snapshot: Snapshot) -> Result<(), String> {
+        self.pendulum = snapshot.pendulum()?;
+        self.root_position = snapshot.root_position;
+        self.root_velocity = snapshot.root_velocity;
+        self.time = snapshot.time;
+        self.reset_state(snapshot.position, snapshot.velocity);
+        // the wall clock of the page that took the snapshot is meaningless here
+        self.last_tick = None;
+        // samples of another chain cannot be mixed in
+        self.recording = false;
+        Ok(())
+    }
+
+    /// Start an `InputLog` from the current state, without frames yet
+    pub fn input_log(&self) -> Result<InputLog, String> {
+        Ok(InputLog {
+            snapshot: self.snapshot()?,
+            last_tick: self.last_tick,
+            camera: self.camera.clone(),
+            grab: self.grab,
+            flick: self.flick,
+            selected: self.selected,
+            integrator: self.integrator,
+            substeps: self.substeps,
+            accumulator: self.accumulator,
+            previous_root_position: self.previous_root_position,
+            previous_position: self.previous_position.clone(),
+            root_target: self.root_target.clone(),
+            root_limits: self.root_limits,
+            frames: Vec::new(),
+        })
+    }
+
+    /// Go back to the state at the start of `log`, ready to `play` its frames
+    pub fn restore_log(&mut self, log: &InputLog) -> Result<(), String> {
+        self.restore(log.snapshot.clone())?;
+        self.last_tick = log.last_tick;
+        self.camera = log.camera.clone();
+        self.grab = log.grab;
+        self.flick = log.flick;
+        self.selected = log.selected;
+        self.integrator = log.integrator;
+        self.substeps = log.substeps.max(1);
+        self.accumulator = log.accumulator;
+        self.previous_root_position = log.previous_root_position;
+        self.previous_position.clone_from(&log.previous_position);
+        self.root_target = log.root_target.clone();
+        self.root_limits = log.root_limits;
+        Ok(())
+    }
+
+    /// Jump to `position` and `velocity` without interpolating from the old state
+    pub fn reset_state(&mut self, position: Vec<Vector3<f64>>, velocity: Vec<Vector3<f64>>) {
+        self.pendulum
+            .set_free_root(self.time, self.root_position, self.root_velocity);
+        self.position = position;
+        self.velocity = velocity;
+        self.accumulator = 0.0;
+        self.previous_root_position = self.root_position;
+        self.previous_position.clone_from(&self.position);
+        self.grab = None;
+        self.flick = None;
+        self.selected = self.selected.filter(|&i| i < self.position.len());
+        self.trails.resize(self.position.len());
+    }
+
+    /// The root and the bobs interpolated by `alpha` in [0, 1) past the last physics step
+    pub fn interpolated(&self, alpha: f64) -> (Vector3<f64>, Vec<Vector3<f64>>) {
+        let lerp = |a: Vector3<f64>, b: Vector3<f64>| a + (b - a) * alpha;
+        let position = self
+            .previous_position
+            .iter()
+            .zip(self.position.iter())
+            .map(|(&a, &b)| lerp(a, b))
+            .collect();
+        (
+            lerp(self.previous_root_position, self.root_position),
+            position,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    fn frame(i: u32, pointers: &Pointers) -> Frame {
+        Frame {
+            timestamp_ms: i as f64 * 16.0,
+            pointers: pointers.clone(),
+            dt: PHYSICS_STEP,
+            size: (640, 480),
+            events: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_grab_on_press() {
+        let mut session = Session::new().unwrap();
+        let mut pointers = Pointers::new();
+        // the root is at the centre of the canvas, 40 px off the pointer
+        pointers.add_pointerdown(1, 360, 240, 0, "mouse");
+        session.play(&frame(0, &pointers)).unwrap();
+        pointers.end_frame();
+        assert!(!session.grabbing());
+
+        // the view brings the root under the held pointer, which does not take it
+        session.camera.pan((40.0 / 480.0, 0.0));
+        session.play(&frame(1, &pointers)).unwrap();
+        pointers.end_frame();
+        assert!(!session.grabbing());
+        assert_eq!(session.root_position, Vector3::zero());
+
+        // pressed again on the root
+        pointers.add_pointerup(1, 360, 240);
+        session.play(&frame(2, &pointers)).unwrap();
+        pointers.end_frame();
+        pointers.add_pointerdown(1, 360, 240, 0, "mouse");
+        session.play(&frame(3, &pointers)).unwrap();
+        assert!(session.grabbing());
+    }
+
+    #[test]
+    fn test_trail_behind_head() {
+        let mut session = Session::new().unwrap();
+        session.reset_pose(Pose::Horizontal).unwrap();
+        let mut frame = frame(0, &Pointers::new());
+        frame.dt = PHYSICS_STEP * 2.5;
+        let (_, head) = session.play(&frame).unwrap();
+
+        // zero width puts both sides of the ribbon on the trail
+        let (vertices, strips) = session.trails.ribbons(&head, vec3(0.0, 0.0, 1.0), 0.0);
+        let (first, count) = strips[3];
+        let point = |i: usize| {
+            let [x, y, z] = vertices[first + i * 2].position;
+            vec3(x as f64, y as f64, z as f64)
+        };
+        let eps = 1e-6;
+        // the last sample is the state before the last step, which the bob is drawn past
+        assert_eq!(count, 3 * 2);
+        assert!((point(1) - session.previous_position[3]).magnitude() < eps);
+        assert!((point(2) - head[3]).magnitude() < eps);
+        let along = (head[3] - point(1)).dot(session.position[3] - point(1));
+        assert!(along > 0.0);
+    }
+}
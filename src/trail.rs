@@ -0,0 +1,191 @@
+use std::collections::VecDeque;
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::renderer::RibbonVertex;
+
+/// Recent positions of each bob, drawn as fading ribbons
+#[derive(Debug, Clone)]
+pub struct Trails {
+    /// samples kept per bob, 0 for no trails
+    length: usize,
+    colors: Vec<[f32; 4]>,
+    history: Vec<VecDeque<Vector3<f64>>>,
+}
+
+impl Trails {
+    pub fn new(bobs: usize, length: usize) -> Trails {
+        let mut trails = Trails {
+            length,
+            colors: Vec::new(),
+            history: Vec::new(),
+        };
+        trails.resize(bobs);
+        trails
+    }
+
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn set_length(&mut self, length: usize) {
+        self.length = length;
+        for h in self.history.iter_mut() {
+            while h.len() > length {
+                h.pop_front();
+            }
+        }
+    }
+
+    /// Colour of the trail of `bob`, alpha 0 to hide it
+    pub fn set_color(&mut self, bob: usize, color: [f32; 4]) -> Result<(), String> {
+        let c = self
+            .colors
+            .get_mut(bob)
+            .ok_or_else(|| format!("no bob {}", bob))?;
+        *c = color;
+        Ok(())
+    }
+
+    /// Forget the history for a chain of `bobs`, keeping the colours of the remaining bobs
+    pub fn resize(&mut self, bobs: usize) {
+        let n = self.colors.len();
+        self.colors.truncate(bobs);
+        self.colors.extend((n..bobs).map(palette));
+        self.history = vec![VecDeque::with_capacity(self.length); bobs];
+    }
+
+    pub fn push(&mut self, position: &[Vector3<f64>]) {
+        if self.length == 0 {
+            return;
+        }
+        for (h, &p) in self.history.iter_mut().zip(position.iter()) {
+            if h.len() == self.length {
+                h.pop_front();
+            }
+            h.push_back(p);
+        }
+    }
+
+    /// Triangle strips of `width` facing `toward_eye`, each as (first, count) in the vertices
+    ///
+    /// Each trail ends at `head`, where the bob is drawn.
+    pub fn ribbons(
+        &self,
+        head: &[Vector3<f64>],
+        toward_eye: Vector3<f64>,
+        width: f64,
+    ) -> (Vec<RibbonVertex>, Vec<(usize, usize)>) {
+        let mut vertices = Vec::new();
+        let mut strips = Vec::new();
+        for ((h, &color), &head) in self.history.iter().zip(self.colors.iter()).zip(head) {
+            if color[3] <= 0.0 || h.is_empty() {
+                continue;
+            }
+            let points = h.iter().copied().chain(Some(head)).collect::<Vec<_>>();
+            let first = vertices.len();
+            let mut side = Vector3::unit_x();
+            for (i, &p) in points.iter().enumerate() {
+                let tangent = points[(i + 1).min(points.len() - 1)] - points[i.saturating_sub(1)];
+                let s = tangent.cross(toward_eye);
+                // keep the last side where the trail stops or runs toward the eye
+                if s.magnitude2() > 1e-20 {
+                    side = s.normalize();
+                }
+                let alpha = color[3] * i as f32 / (points.len() - 1).max(1) as f32;
+                let color = [color[0], color[1], color[2], alpha];
+                for offset in [side * (width / 2.0), -side * (width / 2.0)] {
+                    let q = p + offset;
+                    vertices.push(RibbonVertex {
+                        position: [q.x as f32, q.y as f32, q.z as f32],
+                        color,
+                    });
+                }
+            }
+            strips.push((first, vertices.len() - first));
+        }
+        (vertices, strips)
+    }
+}
+
+/// Distinct colours around the hue circle
+fn palette(i: usize) -> [f32; 4] {
+    let hue = (i as f32 * 0.381_966) % 1.0 * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    let (r, g, b) = match hue as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    [r, g, b, 0.8]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::vec3;
+
+    fn frame(k: usize) -> Vec<Vector3<f64>> {
+        (0..3).map(|i| vec3(k as f64, i as f64, 0.0)).collect()
+    }
+
+    #[test]
+    fn test_length() {
+        let mut trails = Trails::new(3, 4);
+        for k in 0..6 {
+            trails.push(&frame(k));
+        }
+        for (i, h) in trails.history.iter().enumerate() {
+            assert_eq!(
+                h.iter().map(|p| p.x).collect::<Vec<_>>(),
+                [2.0, 3.0, 4.0, 5.0]
+            );
+            assert!(h.iter().all(|p| p.y == i as f64));
+        }
+        // the newest samples are kept
+        trails.set_length(2);
+        assert!(trails.history.iter().all(|h| h.len() == 2 && h[0].x == 4.0));
+        trails.set_length(0);
+        trails.push(&frame(6));
+        assert!(trails.history.iter().all(|h| h.is_empty()));
+
+        assert!(trails.set_color(2, [1.0; 4]).is_ok());
+        assert!(trails.set_color(3, [1.0; 4]).is_err());
+        trails.resize(2);
+        assert_eq!(trails.history.len(), 2);
+    }
+
+    #[test]
+    fn test_ribbons() {
+        let mut trails = Trails::new(3, 4);
+        let head = frame(2);
+        let toward_eye = vec3(0.0, 0.0, 1.0);
+        let (vertices, strips) = trails.ribbons(&head, toward_eye, 0.1);
+        assert!(vertices.is_empty() && strips.is_empty());
+
+        trails.push(&frame(0));
+        trails.push(&frame(1));
+        trails.set_color(1, [1.0, 1.0, 1.0, 0.0]).unwrap();
+        let (vertices, strips) = trails.ribbons(&head, toward_eye, 0.1);
+        // 2 vertices for each of the 2 samples and the head
+        assert_eq!(strips, [(0, 6), (6, 6)]);
+        assert_eq!(vertices.len(), 12);
+        for &(first, count) in strips.iter() {
+            let strip = &vertices[first..first + count];
+            assert_eq!(strip[0].color[3], 0.0);
+            assert_eq!(strip[count - 1].color[3], 0.8);
+            // across the trail along x, facing +z
+            for pair in strip.chunks(2) {
+                let d = [0, 1, 2].map(|k| pair[0].position[k] - pair[1].position[k]);
+                assert!(d[0].abs() < 1e-6 && (d[1].abs() - 0.1).abs() < 1e-6 && d[2] == 0.0);
+            }
+        }
+        // the last trail ends on either side of its head
+        let ends = [vertices[10].position, vertices[11].position];
+        assert!(ends.iter().all(|p| p[0] == 2.0));
+        assert!((ends[0][1] + ends[1][1] - 4.0).abs() < 1e-6);
+    }
+}
@@ -1,71 +1,206 @@
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+/// Farthest a pointer can move between down and up to count as a tap [px]
+const TAP_SLOP: i32 = 8;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointerKind {
+    Mouse,
+    Touch,
+    Pen,
+}
+
+/// A mouse button, finger or pen in contact with the canvas
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Pointer {
+    pub id: i32,
+    pub kind: PointerKind,
+    /// `MouseEvent.button`: 0 for the main button, touches and pen tips
+    pub button: i32,
+    pub start: (i32, i32),
+    /// position at the end of the previous frame
+    pub last: (i32, i32),
+    pub current: (i32, i32),
+    /// lifted during this frame, gone in the next
+    pub released: bool,
+}
+
+/// Every pointer on the canvas, fed by pointer events between frames
 #[wasm_bindgen]
-#[derive(Default, Debug)]
-pub struct Mouse {
-    button_downed: [Option<(i32, i32)>; 3],
-    last_position: Option<(i32, i32)>,
-    current_position: Option<(i32, i32)>,
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub struct Pointers {
+    pointers: Vec<Pointer>,
+    /// `WheelEvent.deltaY` summed over the frame [px]
+    wheel: f64,
+}
+
+/// Motion of two fingers during a frame
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Gesture {
+    /// move of the midpoint [px]
+    pub pan: (f64, f64),
+    /// ratio of the distance between the fingers, > 1 when spreading
+    pub scale: f64,
+    /// counterclockwise turn on the screen [rad]
+    pub twist: f64,
 }
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
-pub enum MouseButton {
-    Left = 0,
-    Middle = 1,
-    Right = 2,
+/// Pointer pressed at `start` to flick `bob` if any
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Flick {
+    /// `Pointer::id` of the flicking pointer
+    pub pointer: i32,
+    pub start: (i32, i32),
+    pub bob: Option<usize>,
+}
+
+/// Main pointer pressed on the root to drag it, or elsewhere
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Grab {
+    /// `Pointer::id` of the pressed pointer
+    pub pointer: i32,
+    /// pressed on the root, decided once when pressed
+    pub root: bool,
+}
+
+impl Pointer {
+    /// Move since the previous frame
+    pub fn delta(&self) -> (i32, i32) {
+        (self.current.0 - self.last.0, self.current.1 - self.last.1)
+    }
+
+    /// Moved too far from where it was pressed to be a tap
+    pub fn is_drag(&self) -> bool {
+        let (dx, dy) = (self.current.0 - self.start.0, self.current.1 - self.start.1);
+        dx * dx + dy * dy > TAP_SLOP * TAP_SLOP
+    }
+
+    /// Released this frame close to where it was pressed
+    pub fn is_tap(&self) -> bool {
+        self.released && !self.is_drag()
+    }
 }
 
 #[wasm_bindgen]
-impl Mouse {
+impl Pointers {
     #[wasm_bindgen(constructor)]
-    pub fn new() -> Mouse {
+    pub fn new() -> Pointers {
         Default::default()
     }
 
+    /// `kind` is `PointerEvent.pointerType`
+    #[wasm_bindgen]
+    pub fn add_pointerdown(&mut self, id: i32, x: i32, y: i32, button: i32, kind: &str) {
+        let kind = match kind {
+            "touch" => PointerKind::Touch,
+            "pen" => PointerKind::Pen,
+            _ => PointerKind::Mouse,
+        };
+        self.pointers.retain(|p| p.id != id);
+        self.pointers.push(Pointer {
+            id,
+            kind,
+            button,
+            start: (x, y),
+            last: (x, y),
+            current: (x, y),
+            released: false,
+        });
+    }
+
     #[wasm_bindgen]
-    pub fn add_mousedown(&mut self, x: i32, y: i32, button: i32) {
-        self.add_position(x, y);
-        if 0 <= button && button <= 2 {
-            self.button_downed[button as usize] = Some((x, y));
+    pub fn add_pointermove(&mut self, id: i32, x: i32, y: i32) {
+        if let Some(p) = self.pointers.iter_mut().find(|p| p.id == id) {
+            p.current = (x, y);
         }
     }
 
     #[wasm_bindgen]
-    pub fn add_mouseup(&mut self, x: i32, y: i32, button: i32) {
-        self.add_position(x, y);
-        if 0 <= button && button <= 2 {
-            self.button_downed[button as usize] = None;
+    pub fn add_pointerup(&mut self, id: i32, x: i32, y: i32) {
+        if let Some(p) = self.pointers.iter_mut().find(|p| p.id == id) {
+            p.current = (x, y);
+            p.released = true;
         }
     }
 
+    /// `delta_y` in pixels, positive to scroll down
+    #[wasm_bindgen]
+    pub fn add_wheel(&mut self, delta_y: f64) {
+        self.wheel += delta_y;
+    }
+
+    /// The pointer is gone without being released, e.g. the touch turned into a scroll
     #[wasm_bindgen]
-    pub fn add_mousemove(&mut self, x: i32, y: i32) {
-        self.add_position(x, y);
+    pub fn add_pointercancel(&mut self, id: i32) {
+        self.pointers.retain(|p| p.id != id);
     }
 }
 
-impl Mouse {
-    fn add_position(&mut self, x: i32, y: i32) {
-        if let Some(c) = self.current_position {
-            self.last_position.replace(c);
+impl Pointers {
+    /// Forget the released pointers and start measuring moves from here
+    pub fn end_frame(&mut self) {
+        self.pointers.retain(|p| !p.released);
+        for p in self.pointers.iter_mut() {
+            p.last = p.current;
         }
-        self.current_position.replace((x, y));
+        self.wheel = 0.0;
     }
 
-    pub fn current_position(&self) -> Option<(i32, i32)> {
-        self.current_position
+    pub fn wheel(&self) -> f64 {
+        self.wheel
     }
 
-    pub fn click(&self, button: MouseButton) -> Option<(i32, i32)> {
-        self.button_downed[button as usize]
+    pub fn iter(&self) -> impl Iterator<Item = &Pointer> {
+        self.pointers.iter()
+    }
+
+    pub fn get(&self, id: i32) -> Option<&Pointer> {
+        self.pointers.iter().find(|p| p.id == id)
+    }
+
+    /// Mouse pointer holding `button`
+    pub fn mouse(&self, button: i32) -> Option<&Pointer> {
+        self.pointers
+            .iter()
+            .find(|p| p.kind == PointerKind::Mouse && p.button == button)
+    }
+
+    /// The main pointer when it is the only one, i.e. not a part of a gesture
+    pub fn primary(&self) -> Option<&Pointer> {
+        let mut main = self.pointers.iter().filter(|p| p.button == 0);
+        match (main.next(), main.next()) {
+            (Some(p), None) => Some(p),
+            _ => None,
+        }
     }
 
-    pub fn drag(&self, button: MouseButton) -> Option<(i32, i32)> {
-        if self.button_downed[button as usize].is_none() {
+    /// Pinch, twist and pan of exactly two touches
+    pub fn gesture(&self) -> Option<Gesture> {
+        let mut touches = self
+            .pointers
+            .iter()
+            .filter(|p| p.kind == PointerKind::Touch);
+        let (a, b) = match (touches.next(), touches.next(), touches.next()) {
+            (Some(a), Some(b), None) => (a, b),
+            _ => return None,
+        };
+        let f = |(x, y): (i32, i32)| (x as f64, y as f64);
+        let ((a0x, a0y), (b0x, b0y)) = (f(a.last), f(b.last));
+        let ((a1x, a1y), (b1x, b1y)) = (f(a.current), f(b.current));
+        let d0 = (b0x - a0x).hypot(b0y - a0y);
+        let d1 = (b1x - a1x).hypot(b1y - a1y);
+        if d0 == 0.0 || d1 == 0.0 {
             return None;
         }
-        let (x0, y0) = self.last_position?;
-        let (x1, y1) = self.current_position?;
-        Some((x1 - x0, y1 - y0))
+        // y of the screen points down
+        let twist = (a1y - b1y).atan2(b1x - a1x) - (a0y - b0y).atan2(b0x - a0x);
+        Some(Gesture {
+            pan: ((a1x + b1x - a0x - b0x) / 2.0, (a1y + b1y - a0y - b0y) / 2.0),
+            scale: d1 / d0,
+            twist: (twist + std::f64::consts::PI).rem_euclid(std::f64::consts::TAU)
+                - std::f64::consts::PI,
+        })
     }
 }